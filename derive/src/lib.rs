@@ -0,0 +1,80 @@
+//! Derive macros for converting between structs and `rusttest::enum_df` rows.
+//!
+//! Each named field of the struct maps to a dataframe column of the same name,
+//! the field type must implement `rusttest::rows::ColumnType`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+
+/// named fields of a struct, or a compile error pointing at the input
+fn named_fields(input: &DeriveInput) -> Result<Vec<&Ident>, TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(input, "rows can only be derived for structs")
+            .to_compile_error());
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(input, "rows can only be derived for structs with named fields")
+            .to_compile_error());
+    };
+    Ok(fields.named.iter().map(|f| f.ident.as_ref().unwrap()).collect())
+}
+
+#[proc_macro_derive(FromRow)]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match named_fields(&input) {
+        Ok(f) => f,
+        Err(e) => return e.into(),
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let names = fields.iter().map(|f| f.to_string());
+
+    quote! {
+        impl #impl_generics ::rusttest::rows::FromRow for #name #ty_generics #where_clause {
+            fn from_row(
+                df: &::rusttest::enum_df::DataFrame,
+                row: usize,
+            ) -> ::std::result::Result<Self, ::rusttest::rows::RowError> {
+                ::std::result::Result::Ok(Self {
+                    #( #fields: ::rusttest::rows::get_field(df, #names, row)?, )*
+                })
+            }
+        }
+    }
+    .into()
+}
+
+#[proc_macro_derive(IntoRows)]
+pub fn derive_into_rows(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match named_fields(&input) {
+        Ok(f) => f,
+        Err(e) => return e.into(),
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let names = fields.iter().map(|f| f.to_string());
+    // prefixed so field names cannot shadow the locals used below
+    let cols: Vec<_> = fields.iter().map(|f| format_ident!("__col_{}", f)).collect();
+
+    quote! {
+        impl #impl_generics ::rusttest::rows::IntoRows for #name #ty_generics #where_clause {
+            fn into_frame(rows: ::std::vec::Vec<Self>) -> ::rusttest::enum_df::DataFrame {
+                #( let mut #cols = ::std::vec::Vec::with_capacity(rows.len()); )*
+                for __row in rows {
+                    #( #cols.push(__row.#fields); )*
+                }
+                let mut __df = ::rusttest::enum_df::DataFrame::new();
+                #(
+                    __df.addcol(#names, ::rusttest::rows::ColumnType::into_column(#cols))
+                        .expect("fields of every row have the same length");
+                )*
+                __df
+            }
+        }
+    }
+    .into()
+}
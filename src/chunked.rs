@@ -0,0 +1,306 @@
+//! Chunked column storage for data that arrives in batches.
+//!
+//! A `ChunkedColumn` keeps each appended batch as its own contiguous `Column`,
+//! so appending never moves data already stored. Typed iteration and kernels
+//! run chunk by chunk, `rechunk` merges everything into one chunk when a single
+//! contiguous buffer is needed.
+
+use std::collections::HashMap;
+
+use crate::compute;
+use crate::enum_df::{Column, DataFrame};
+use crate::nested::{ListColumn, StructColumn};
+use crate::numeric::Numeric;
+use crate::rows::ColumnType;
+
+#[derive(Clone, Debug, Default)]
+pub struct ChunkedColumn {
+    len: usize,
+    chunks: Vec<Column>,
+}
+
+impl From<Column> for ChunkedColumn {
+    fn from(c: Column) -> Self {
+        ChunkedColumn { len: c.len(), chunks: vec![c] }
+    }
+}
+
+impl ChunkedColumn {
+    pub fn new() -> ChunkedColumn {
+        ChunkedColumn { len: 0, chunks: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn n_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn chunks(&self) -> &[Column] {
+        &self.chunks
+    }
+
+    /// element type of the column, None until the first chunk is appended
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.chunks.first().map(Column::type_name)
+    }
+
+    /// add a chunk to the end of the column without touching existing data
+    pub fn append(&mut self, c: Column) -> Result<(), &'static str> {
+        if self.chunks.first().is_some_and(|f| !f.same_type(&c)) {
+            return Err("chunk type does not match column type");
+        }
+        if !c.is_empty() || self.chunks.is_empty() {
+            self.len += c.len();
+            self.chunks.push(c);
+        }
+        Ok(())
+    }
+
+    /// merge all chunks into a single contiguous chunk, None if nothing has
+    /// been appended yet
+    pub fn rechunk(&mut self) -> Option<&Column> {
+        if self.chunks.len() > 1 {
+            let merged = concat(std::mem::take(&mut self.chunks), self.len);
+            self.chunks.push(merged);
+        }
+        self.chunks.first()
+    }
+
+    /// rechunk and return the single contiguous column, None if nothing has
+    /// been appended yet
+    pub fn into_column(mut self) -> Option<Column> {
+        self.rechunk();
+        self.chunks.pop()
+    }
+
+    /// iterate over values across all chunks, None if the column holds a
+    /// different type
+    pub fn iter<T: ColumnType>(&self) -> Option<impl Iterator<Item = &T>> {
+        let slices = self.chunks.iter().map(T::slice).collect::<Option<Vec<_>>>()?;
+        Some(slices.into_iter().flatten())
+    }
+
+    /// mutable access to each chunk's data, for running slice kernels such as
+    /// those in `compute` over the whole column
+    pub fn chunks_mut<T: ColumnType>(&mut self) -> Option<Vec<&mut [T]>> {
+        self.chunks.iter_mut().map(T::slice_mut).collect()
+    }
+
+    /// `compute::sum_column` of each chunk, added up
+    pub fn sum(&self, chunk_len: usize) -> Result<f64, String> {
+        self.chunks.iter().map(|c| compute::sum_column(c, chunk_len)).sum()
+    }
+
+    /// `compute::min_max_column` of each chunk, combined, None if there are
+    /// no values
+    pub fn min_max(&self, chunk_len: usize) -> Result<Option<(f64, f64)>, String> {
+        let mut out: Option<(f64, f64)> = None;
+        for c in &self.chunks {
+            out = match (out, compute::min_max_column(c, chunk_len)?) {
+                (Some((lo, hi)), Some((l, h))) => Some((lo.min(l), hi.max(h))),
+                (a, b) => a.or(b),
+            };
+        }
+        Ok(out)
+    }
+
+    /// replace each value `x` by `f(x)` with `compute::par_map`, chunk by
+    /// chunk
+    pub fn map<T, F>(&mut self, chunk_len: usize, f: F) -> Result<(), String>
+    where
+        T: Numeric + ColumnType,
+        F: Fn(T) -> T + Sync,
+    {
+        let type_name = self.type_name();
+        let chunks = self.chunks_mut::<T>()
+            .ok_or_else(|| format!("expected a {} column, not {}", T::NAME, type_name.unwrap_or_default()))?;
+        for c in chunks {
+            compute::par_map(c, chunk_len, &f);
+        }
+        Ok(())
+    }
+}
+
+/// join same-typed chunks into one column of `len` rows
+pub(crate) fn concat(chunks: Vec<Column>, len: usize) -> Column {
+    macro_rules! concat_as {
+        ($variant:ident) => {{
+            let mut v = Vec::with_capacity(len);
+            for c in chunks {
+                let Column::$variant(x) = c else { unreachable!("chunk types are checked on append") };
+                v.extend(x);
+            }
+            Column::$variant(v)
+        }};
+    }
+    match chunks[0] {
+        Column::F(_) => concat_as!(F),
+        Column::D(_) => concat_as!(D),
+        Column::I(_) => concat_as!(I),
+        Column::S(_) => concat_as!(S),
+        Column::L(_) => Column::L(ListColumn::concat(chunks.into_iter().map(|c| match c {
+            Column::L(l) => l,
+            _ => unreachable!("chunk types are checked on append"),
+        }).collect())),
+        Column::R(_) => Column::R(StructColumn::concat(chunks.into_iter().map(|c| match c {
+            Column::R(s) => s,
+            _ => unreachable!("chunk types are checked on append"),
+        }).collect())),
+    }
+}
+
+/// Dataframe built up from batches with the same columns, each column stored
+/// as a `ChunkedColumn`
+#[derive(Default)]
+pub struct ChunkedFrame {
+    len: usize,
+    columns: HashMap<String, ChunkedColumn>,
+    names: Vec<String>,
+}
+
+impl ChunkedFrame {
+    pub fn new() -> ChunkedFrame {
+        ChunkedFrame { len: 0, columns: HashMap::new(), names: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ChunkedColumn> {
+        self.columns.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ChunkedColumn> {
+        self.columns.get_mut(name)
+    }
+
+    /// append a batch, which must have the same column names and types as the
+    /// batches before it
+    pub fn append(&mut self, batch: DataFrame) -> Result<(), &'static str> {
+        if self.columns.is_empty() {
+            self.names = batch.names().map(String::from).collect();
+            self.columns = batch.names().map(|n| (n.to_string(), ChunkedColumn::new())).collect();
+        }
+        let same_names = self.columns.len() == batch.iter().count()
+            && batch.iter().all(|(n, _)| self.columns.contains_key(n));
+        if !same_names {
+            return Err("batch columns do not match frame columns");
+        }
+        let same_types = batch.iter()
+            .all(|(n, c)| self.columns[n].chunks.first().is_none_or(|f| f.same_type(c)));
+        if !same_types {
+            return Err("chunk type does not match column type");
+        }
+        self.len += batch.len();
+        for (n, c) in batch.into_columns() {
+            self.columns.get_mut(&n).unwrap().append(c)?;
+        }
+        Ok(())
+    }
+
+    pub fn rechunk(&mut self) {
+        for c in self.columns.values_mut() {
+            c.rechunk();
+        }
+    }
+
+    /// rechunk every column into a regular dataframe
+    pub fn into_frame(mut self) -> DataFrame {
+        let mut df = DataFrame::new();
+        for n in self.names {
+            if let Some(c) = self.columns.remove(&n).and_then(ChunkedColumn::into_column) {
+                df.addcol(&n, c).expect("chunked columns have equal length");
+            }
+        }
+        df
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compute::process_array_t;
+    use crate::df;
+
+    #[test]
+    fn test_append_and_rechunk() {
+        let mut c = ChunkedColumn::from(Column::I(vec![1, 2]));
+        c.append(Column::I(vec![3])).unwrap();
+        c.append(Column::I(vec![])).unwrap();
+        c.append(Column::I(vec![4, 5])).unwrap();
+        assert_eq!(c.len(), 5);
+        assert_eq!(c.n_chunks(), 3);
+        assert!(c.append(Column::F(vec![1.0])).is_err());
+
+        let v: Vec<i32> = c.iter::<i32>().unwrap().copied().collect();
+        assert_eq!(v, vec![1, 2, 3, 4, 5]);
+        assert!(c.iter::<f32>().is_none());
+
+        let Some(Column::I(x)) = c.rechunk() else { panic!("expected I column") };
+        assert_eq!(x, &vec![1, 2, 3, 4, 5]);
+        assert_eq!(c.n_chunks(), 1);
+
+        let mut empty = ChunkedColumn::new();
+        assert!(empty.rechunk().is_none());
+        assert!(empty.into_column().is_none());
+        assert!(ChunkedColumn::default().into_column().is_none());
+    }
+
+    #[test]
+    fn test_kernel_over_chunks() {
+        let mut c = ChunkedColumn::from(Column::F(vec![0.0, 0.0]));
+        c.append(Column::F(vec![0.0])).unwrap();
+        for chunk in c.chunks_mut::<f32>().unwrap() {
+            process_array_t(chunk);
+        }
+        let total: f32 = c.iter::<f32>().unwrap().sum();
+        assert_eq!(total, 3.0);
+    }
+
+    #[test]
+    fn test_chunked_kernels() {
+        let mut c = ChunkedColumn::from(Column::D(vec![1.0, f64::NAN, -2.0]));
+        c.append(Column::D(vec![5.0, 0.5])).unwrap();
+        assert_eq!(c.sum(2).unwrap(), 4.5);
+        assert_eq!(c.min_max(2).unwrap(), Some((-2.0, 5.0)));
+
+        c.map(2, |x: f64| x * 2.0).unwrap();
+        assert_eq!(c.n_chunks(), 2);
+        assert_eq!(c.sum(2).unwrap(), 9.0);
+        assert!(c.map(2, |x: i32| x + 1).is_err());
+
+        let s = ChunkedColumn::from(Column::S(vec!["x".into()]));
+        assert!(s.sum(2).is_err());
+        assert!(s.min_max(2).is_err());
+        assert_eq!(ChunkedColumn::new().min_max(2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_chunked_frame() {
+        let mut f = ChunkedFrame::new();
+        f.append(df! { "a" => [1, 2], "b" => ["x", "y"] }).unwrap();
+        f.append(df! { "a" => [3], "b" => ["z"] }).unwrap();
+        assert_eq!(f.len(), 3);
+        assert_eq!(f.get("a").unwrap().n_chunks(), 2);
+        assert!(f.append(df! { "a" => [3] }).is_err());
+        assert!(f.append(df! { "a" => [3.0], "b" => ["z"] }).is_err());
+        assert_eq!(f.len(), 3);
+
+        let df = f.into_frame();
+        assert_eq!(df.len(), 3);
+        let Some(Column::S(b)) = df.get("b") else { panic!("expected S column") };
+        assert_eq!(b, &vec!["x", "y", "z"]);
+    }
+}
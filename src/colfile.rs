@@ -0,0 +1,408 @@
+//! Native on-disk columnar format for `enum_df::DataFrame`, read through a
+//! memory map so columns are only paged in when touched.
+//!
+//! Layout, all integers little endian:
+//!
+//! ```text
+//! magic     b"RUSTYCF1"
+//! nrows     u64
+//! ncols     u32
+//! ncols x   name_len u32, name (utf8), type u8, offset u64, nbytes u64
+//! padding   to a multiple of ALIGN
+//! ncols x   column chunk, each starting on a multiple of ALIGN
+//! ```
+//!
+//! Numeric chunks are the raw values. String chunks are `nrows + 1` u64 offsets
+//! followed by the concatenated utf8 bytes, string `i` being
+//! `bytes[offsets[i]..offsets[i + 1]]`.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::enum_df::{Column, DataFrame};
+
+const MAGIC: &[u8; 8] = b"RUSTYCF1";
+const ALIGN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    F = 0,
+    D = 1,
+    I = 2,
+    S = 3,
+}
+
+impl Kind {
+    fn of(c: &Column) -> Kind {
+        match c {
+            Column::F(_) => Kind::F,
+            Column::D(_) => Kind::D,
+            Column::I(_) => Kind::I,
+            Column::S(_) => Kind::S,
+            Column::L(_) | Column::R(_) => unreachable!("nested columns are rejected by write_frame"),
+        }
+    }
+
+    fn from_u8(t: u8) -> io::Result<Kind> {
+        match t {
+            0 => Ok(Kind::F),
+            1 => Ok(Kind::D),
+            2 => Ok(Kind::I),
+            3 => Ok(Kind::S),
+            _ => Err(invalid(format!("unknown column type {}", t))),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Kind::F => "f32",
+            Kind::D => "f64",
+            Kind::I => "i32",
+            Kind::S => "String",
+        }
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn align_up(n: usize) -> usize {
+    n.div_ceil(ALIGN) * ALIGN
+}
+
+fn chunk_len(c: &Column) -> usize {
+    match c {
+        Column::F(x) => x.len() * 4,
+        Column::D(x) => x.len() * 8,
+        Column::I(x) => x.len() * 4,
+        Column::S(x) => (x.len() + 1) * 8 + x.iter().map(String::len).sum::<usize>(),
+        Column::L(_) | Column::R(_) => unreachable!("nested columns are rejected by write_frame"),
+    }
+}
+
+fn write_chunk<W: Write>(w: &mut W, c: &Column) -> io::Result<()> {
+    match c {
+        Column::F(x) => x.iter().try_for_each(|v| w.write_all(&v.to_le_bytes())),
+        Column::D(x) => x.iter().try_for_each(|v| w.write_all(&v.to_le_bytes())),
+        Column::I(x) => x.iter().try_for_each(|v| w.write_all(&v.to_le_bytes())),
+        Column::S(x) => {
+            let mut off = 0u64;
+            w.write_all(&off.to_le_bytes())?;
+            for s in x {
+                off += s.len() as u64;
+                w.write_all(&off.to_le_bytes())?;
+            }
+            x.iter().try_for_each(|s| w.write_all(s.as_bytes()))
+        }
+        Column::L(_) | Column::R(_) => unreachable!("nested columns are rejected by write_frame"),
+    }
+}
+
+/// Write a dataframe to `path` in the columnar format
+pub fn write_frame<P: AsRef<Path>>(df: &DataFrame, path: P) -> io::Result<()> {
+    let cols: Vec<(&str, &Column)> = df.iter().collect();
+    if let Some((name, _)) = cols.iter().find(|(_, c)| matches!(c, Column::L(_) | Column::R(_))) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported, format!("column '{}': nested columns cannot be stored", name)
+        ));
+    }
+    let header_len = MAGIC.len() + 8 + 4
+        + cols.iter().map(|(n, _)| 4 + n.len() + 1 + 8 + 8).sum::<usize>();
+
+    let mut w = BufWriter::new(File::create(path)?);
+    w.write_all(MAGIC)?;
+    w.write_all(&(df.len() as u64).to_le_bytes())?;
+    w.write_all(&(cols.len() as u32).to_le_bytes())?;
+    let mut offset = align_up(header_len);
+    for (name, c) in &cols {
+        let nbytes = chunk_len(c);
+        w.write_all(&(name.len() as u32).to_le_bytes())?;
+        w.write_all(name.as_bytes())?;
+        w.write_all(&[Kind::of(c) as u8])?;
+        w.write_all(&(offset as u64).to_le_bytes())?;
+        w.write_all(&(nbytes as u64).to_le_bytes())?;
+        offset = align_up(offset + nbytes);
+    }
+
+    let mut pos = header_len;
+    for (_, c) in &cols {
+        w.write_all(&vec![0; align_up(pos) - pos])?;
+        write_chunk(&mut w, c)?;
+        pos = align_up(pos) + chunk_len(c);
+    }
+    w.flush()
+}
+
+struct ColumnMeta {
+    name: String,
+    kind: Kind,
+    offset: usize,
+    nbytes: usize,
+}
+
+/// Cursor for parsing the header, every read is bounds checked
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n)
+            .filter(|e| *e <= self.buf.len())
+            .ok_or_else(|| invalid("truncated header".to_string()))?;
+        let b = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// A column file opened through a memory map. Opening only parses the header,
+/// column data is read when asked for.
+pub struct MappedFrame {
+    map: Mmap,
+    len: usize,
+    columns: Vec<ColumnMeta>,
+}
+
+impl MappedFrame {
+    /// Map the file at `path`. The file must not be modified while mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFrame> {
+        let file = File::open(path)?;
+        // safety: the map is read only and we require the file is not changed
+        // underneath us, as documented above
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut r = Reader { buf: &map, pos: 0 };
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a column file".to_string()));
+        }
+        let len = r.u64()? as usize;
+        let ncols = r.u32()? as usize;
+        let mut columns = Vec::with_capacity(ncols);
+        for _ in 0..ncols {
+            let name_len = r.u32()? as usize;
+            let name = String::from_utf8(r.take(name_len)?.to_vec())
+                .map_err(|_| invalid("column name is not utf8".to_string()))?;
+            let kind = Kind::from_u8(r.u8()?)?;
+            let offset = r.u64()? as usize;
+            let nbytes = r.u64()? as usize;
+            if !offset.is_multiple_of(ALIGN) || offset.checked_add(nbytes).is_none_or(|e| e > map.len()) {
+                return Err(invalid(format!("column '{}' chunk out of bounds", name)));
+            }
+            let ok = match kind {
+                Kind::F | Kind::I => len.checked_mul(4) == Some(nbytes),
+                Kind::D => len.checked_mul(8) == Some(nbytes),
+                Kind::S => len.checked_add(1).and_then(|n| n.checked_mul(8)).is_some_and(|n| n <= nbytes),
+            };
+            if !ok {
+                return Err(invalid(format!("column '{}' chunk has wrong size", name)));
+            }
+            columns.push(ColumnMeta { name, kind, offset, nbytes });
+        }
+        Ok(MappedFrame { map, len, columns })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// (name, element type) of each column, in file order
+    pub fn schema(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        self.columns.iter().map(|m| (m.name.as_str(), m.kind.type_name()))
+    }
+
+    fn meta(&self, name: &str) -> Option<&ColumnMeta> {
+        self.columns.iter().find(|m| m.name == name)
+    }
+
+    fn chunk(&self, m: &ColumnMeta) -> &[u8] {
+        &self.map[m.offset..m.offset + m.nbytes]
+    }
+
+    /// Zero-copy access to a column's data in the map. Pages are read from
+    /// disk as the returned slices are touched.
+    pub fn column(&self, name: &str) -> Option<MappedColumn<'_>> {
+        let m = self.meta(name)?;
+        let b = self.chunk(m);
+        Some(match m.kind {
+            Kind::F => MappedColumn::F(cast(b)),
+            Kind::D => MappedColumn::D(cast(b)),
+            Kind::I => MappedColumn::I(cast(b)),
+            Kind::S => {
+                let (offsets, bytes) = b.split_at((self.len + 1) * 8);
+                MappedColumn::S(MappedStrings { offsets: cast(offsets), bytes })
+            }
+        })
+    }
+
+    /// Copy one column into memory
+    pub fn read_column(&self, name: &str) -> io::Result<Column> {
+        let c = self.column(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no column named '{}'", name)))?;
+        c.to_column()
+    }
+
+    /// Load the named columns into an in-memory dataframe
+    pub fn to_frame(&self, names: &[&str]) -> io::Result<DataFrame> {
+        let mut df = DataFrame::new();
+        for name in names {
+            df.addcol(name, self.read_column(name)?).expect("columns in a file have equal length");
+        }
+        Ok(df)
+    }
+}
+
+/// view a little endian byte chunk as a slice of `T`. Chunks start on ALIGN
+/// boundaries of a page aligned map and their sizes are checked on open. The
+/// module is only built for little endian targets so no byte swapping is needed.
+fn cast<T>(b: &[u8]) -> &[T] {
+    assert_eq!(b.as_ptr() as usize % std::mem::align_of::<T>(), 0);
+    assert_eq!(b.len() % std::mem::size_of::<T>(), 0);
+    // safety: alignment and length checked above, and T is only ever one of the
+    // plain numeric types for which every bit pattern is valid
+    unsafe { std::slice::from_raw_parts(b.as_ptr() as *const T, b.len() / std::mem::size_of::<T>()) }
+}
+
+/// Column data borrowed from a `MappedFrame`
+pub enum MappedColumn<'a> {
+    F(&'a [f32]),
+    D(&'a [f64]),
+    I(&'a [i32]),
+    S(MappedStrings<'a>),
+}
+
+impl MappedColumn<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            MappedColumn::F(x) => x.len(),
+            MappedColumn::D(x) => x.len(),
+            MappedColumn::I(x) => x.len(),
+            MappedColumn::S(x) => x.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_column(&self) -> io::Result<Column> {
+        Ok(match self {
+            MappedColumn::F(x) => Column::F(x.to_vec()),
+            MappedColumn::D(x) => Column::D(x.to_vec()),
+            MappedColumn::I(x) => Column::I(x.to_vec()),
+            MappedColumn::S(x) => Column::S(
+                (0..x.len()).map(|i| x.get(i).map(String::from)).collect::<io::Result<_>>()?,
+            ),
+        })
+    }
+}
+
+/// Strings of a mapped column, decoded on access
+pub struct MappedStrings<'a> {
+    offsets: &'a [u64],
+    bytes: &'a [u8],
+}
+
+impl<'a> MappedStrings<'a> {
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> io::Result<&'a str> {
+        let (start, end) = (self.offsets[i] as usize, self.offsets[i + 1] as usize);
+        let b = self.bytes.get(start..end)
+            .ok_or_else(|| invalid(format!("string {} out of bounds", i)))?;
+        std::str::from_utf8(b).map_err(|_| invalid(format!("string {} is not utf8", i)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+
+    /// temp file path removed on drop
+    struct TempPath(std::path::PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> TempPath {
+            TempPath(std::env::temp_dir().join(format!("rusttest-{}-{}.col", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = TempPath::new("round_trip");
+        let df = df! {
+            "f" => [1.0f32, 2.0, 3.0],
+            "d" => [0.5, 1.5, 2.5],
+            "i" => [1, 2, 3],
+            "s" => ["a", "", "ccc"],
+        };
+        write_frame(&df, &path.0).unwrap();
+
+        let mf = MappedFrame::open(&path.0).unwrap();
+        assert_eq!(mf.len(), 3);
+        let mut schema: Vec<_> = mf.schema().collect();
+        schema.sort();
+        assert_eq!(schema, vec![("d", "f64"), ("f", "f32"), ("i", "i32"), ("s", "String")]);
+
+        let Some(MappedColumn::D(d)) = mf.column("d") else { panic!("expected D column") };
+        assert_eq!(d, &[0.5, 1.5, 2.5]);
+        let Some(MappedColumn::S(s)) = mf.column("s") else { panic!("expected S column") };
+        assert_eq!(s.get(1).unwrap(), "");
+        assert_eq!(s.get(2).unwrap(), "ccc");
+
+        let back = mf.to_frame(&["i", "s"]).unwrap();
+        assert_eq!(back.len(), 3);
+        let Some(Column::I(i)) = back.get("i") else { panic!("expected I column") };
+        assert_eq!(i, &vec![1, 2, 3]);
+        assert!(back.get("f").is_none());
+        assert!(mf.read_column("missing").is_err());
+    }
+
+    #[test]
+    fn test_bad_file() {
+        let path = TempPath::new("bad_file");
+        std::fs::write(&path.0, b"not a column file").unwrap();
+        assert!(MappedFrame::open(&path.0).is_err());
+
+        let df = df! { "i" => [1, 2, 3] };
+        write_frame(&df, &path.0).unwrap();
+        let mut bytes = std::fs::read(&path.0).unwrap();
+        bytes.truncate(bytes.len() - 4);
+        std::fs::write(&path.0, bytes).unwrap();
+        assert!(MappedFrame::open(&path.0).is_err());
+    }
+}
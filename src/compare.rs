@@ -0,0 +1,393 @@
+//! Comparing `enum_df` frames.
+//!
+//! `PartialEq` on `Column` and `DataFrame` is exact, so floats must match bit
+//! for bit and NaN never equals NaN. The functions here compare floats within
+//! a tolerance, can ignore row and column order, and `diff` reports which rows
+//! and cells differ between two frames keyed by some of their columns.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::enum_df::{Column, DataFrame};
+use crate::groupby::numeric_at;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CompareOptions {
+    /// relative tolerance for float values
+    pub rtol: f64,
+    /// absolute tolerance for float values
+    pub atol: f64,
+    /// treat NaN as equal to NaN
+    pub nan_equal: bool,
+    /// require matching column types, otherwise numeric columns compare by
+    /// value whatever their type
+    pub check_dtype: bool,
+    pub check_column_order: bool,
+    /// require matching row order, otherwise both frames are sorted by all
+    /// columns before comparing
+    pub check_row_order: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            rtol: 1e-5,
+            atol: 1e-8,
+            nan_equal: true,
+            check_dtype: true,
+            check_column_order: true,
+            check_row_order: true,
+        }
+    }
+}
+
+impl CompareOptions {
+    /// no float tolerance, NaN still equals NaN
+    pub fn exact() -> Self {
+        CompareOptions { rtol: 0.0, atol: 0.0, ..Default::default() }
+    }
+}
+
+fn close(a: f64, b: f64, opts: &CompareOptions) -> bool {
+    if a.is_nan() || b.is_nan() {
+        return opts.nan_equal && a.is_nan() && b.is_nan();
+    }
+    a == b || (a - b).abs() <= opts.atol + opts.rtol * b.abs()
+}
+
+/// whether row `i` of `a` equals row `j` of `b`
+fn cell_eq(a: &Column, i: usize, b: &Column, j: usize, opts: &CompareOptions) -> bool {
+    match (a, b) {
+        (Column::S(x), Column::S(y)) => x[i] == y[j],
+        (Column::L(x), Column::L(y)) => {
+            let (u, v) = (x.get(i).to_column(), y.get(j).to_column());
+            column_mismatch(&u, &v, opts).is_none()
+        }
+        (Column::R(x), Column::R(y)) => {
+            x.fields().count() == y.fields().count()
+                && x.fields().all(|(n, c)| y.field(n).is_some_and(|d| cell_eq(c, i, d, j, opts)))
+        }
+        _ => match (numeric_at(a, i), numeric_at(b, j)) {
+            (Some(x), Some(y)) => close(x, y, opts),
+            _ => false,
+        },
+    }
+}
+
+fn types_match(a: &Column, b: &Column, opts: &CompareOptions) -> bool {
+    a.same_type(b) || (!opts.check_dtype && a.is_numeric() && b.is_numeric())
+}
+
+/// why column `a` differs from `b`, or None if they are equal under `opts`
+fn column_mismatch(a: &Column, b: &Column, opts: &CompareOptions) -> Option<String> {
+    if !types_match(a, b, opts) {
+        return Some(format!("types differ: {} != {}", a.type_name(), b.type_name()));
+    }
+    if a.len() != b.len() {
+        return Some(format!("lengths differ: {} != {}", a.len(), b.len()));
+    }
+    let (u, v) = (a.view(), b.view());
+    (0..a.len()).find(|i| !cell_eq(a, *i, b, *i, opts))
+        .map(|i| format!("row {}: {} != {}", i, u.fmt_value(i), v.fmt_value(i)))
+}
+
+fn cmp_cell(c: &Column, i: usize, j: usize) -> Ordering {
+    match c {
+        Column::S(x) => x[i].cmp(&x[j]),
+        Column::L(_) | Column::R(_) => {
+            let v = c.view();
+            v.fmt_value(i).cmp(&v.fmt_value(j))
+        }
+        _ => numeric_at(c, i).unwrap().total_cmp(&numeric_at(c, j).unwrap()),
+    }
+}
+
+/// row indices of `df` sorted by the `names` columns in turn
+fn sorted_rows(df: &DataFrame, names: &[&str]) -> Vec<usize> {
+    let cols: Vec<&Column> = names.iter().map(|n| df.get(n).unwrap()).collect();
+    let mut idx: Vec<usize> = (0..df.len()).collect();
+    idx.sort_by(|i, j| cols.iter().map(|c| cmp_cell(c, *i, *j)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal));
+    idx
+}
+
+/// true if the columns are equal under `opts`
+pub fn columns_equal(a: &Column, b: &Column, opts: &CompareOptions) -> bool {
+    column_mismatch(a, b, opts).is_none()
+}
+
+/// the first difference found between `left` and `right`, or None if they are
+/// equal under `opts`
+pub fn frame_mismatch(left: &DataFrame, right: &DataFrame, opts: &CompareOptions) -> Option<String> {
+    let ln: Vec<&str> = left.names().collect();
+    let rn: Vec<&str> = right.names().collect();
+    let same_names = if opts.check_column_order {
+        ln == rn
+    } else {
+        ln.len() == rn.len() && ln.iter().all(|n| rn.contains(n))
+    };
+    if !same_names {
+        return Some(format!("columns differ: {:?} != {:?}", ln, rn));
+    }
+    if left.len() != right.len() {
+        return Some(format!("row counts differ: {} != {}", left.len(), right.len()));
+    }
+    for n in &ln {
+        let (a, b) = (left.get(n).unwrap(), right.get(n).unwrap());
+        if !types_match(a, b, opts) {
+            return Some(format!("column '{}' types differ: {} != {}", n, a.type_name(), b.type_name()));
+        }
+    }
+
+    let (li, ri) = if opts.check_row_order {
+        ((0..left.len()).collect(), (0..right.len()).collect())
+    } else {
+        (sorted_rows(left, &ln), sorted_rows(right, &ln))
+    };
+    for n in &ln {
+        let (a, b) = (left.get(n).unwrap(), right.get(n).unwrap());
+        if let Some(k) = (0..li.len()).find(|k| !cell_eq(a, li[*k], b, ri[*k], opts)) {
+            let (i, j) = (li[k], ri[k]);
+            return Some(format!("column '{}' row {}: {} != {}", n, i, a.view().fmt_value(i), b.view().fmt_value(j)));
+        }
+    }
+    None
+}
+
+/// true if the frames are equal under `opts`
+pub fn frames_equal(left: &DataFrame, right: &DataFrame, opts: &CompareOptions) -> bool {
+    frame_mismatch(left, right, opts).is_none()
+}
+
+/// panic with the first difference and both frames unless they are equal under
+/// `opts`
+#[track_caller]
+pub fn assert_frame_equal(left: &DataFrame, right: &DataFrame, opts: &CompareOptions) {
+    if let Some(why) = frame_mismatch(left, right, opts) {
+        panic!("frames are not equal: {}\nleft:\n{}\nright:\n{}", why, left, right);
+    }
+}
+
+/// a value that differs between two rows with the same key
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellChange {
+    /// the key columns' values, formatted
+    pub key: Vec<String>,
+    pub column: String,
+    pub left: String,
+    pub right: String,
+}
+
+/// Result of `diff`, describing how to get from the left frame to the right
+#[derive(Debug)]
+pub struct FrameDiff {
+    /// rows of the right frame whose key is not in the left one
+    pub added: DataFrame,
+    /// rows of the left frame whose key is not in the right one
+    pub removed: DataFrame,
+    /// differing values of rows present in both, for columns present in both
+    pub changed: Vec<CellChange>,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+}
+
+impl FrameDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+            && self.added_columns.is_empty() && self.removed_columns.is_empty()
+    }
+}
+
+impl fmt::Display for FrameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        let mut parts = Vec::new();
+        if !self.added_columns.is_empty() {
+            parts.push(format!("added columns: {}", self.added_columns.join(", ")));
+        }
+        if !self.removed_columns.is_empty() {
+            parts.push(format!("removed columns: {}", self.removed_columns.join(", ")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("removed {} rows:\n{}", self.removed.len(), self.removed.view()));
+        }
+        if !self.added.is_empty() {
+            parts.push(format!("added {} rows:\n{}", self.added.len(), self.added.view()));
+        }
+        if !self.changed.is_empty() {
+            let mut s = format!("changed {} cells:", self.changed.len());
+            for c in &self.changed {
+                s += &format!("\n  [{}] {}: {} -> {}", c.key.join(", "), c.column, c.left, c.right);
+            }
+            parts.push(s);
+        }
+        write!(f, "{}", parts.join("\n"))
+    }
+}
+
+/// A key value matched by value: numbers of any type as f64 bits, so `1` and
+/// `1.0` are the same key, and other values as strings
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum KeyVal {
+    Num(u64),
+    Str(String),
+}
+
+fn key_val(c: &Column, i: usize) -> KeyVal {
+    match (numeric_at(c, i), c) {
+        // one bit pattern for zero and for NaN
+        (Some(x), _) if x.is_nan() => KeyVal::Num(f64::NAN.to_bits()),
+        (Some(x), _) => KeyVal::Num(if x == 0.0 { 0 } else { x.to_bits() }),
+        (None, Column::S(x)) => KeyVal::Str(x[i].clone()),
+        (None, c) => KeyVal::Str(c.view().fmt_value(i)),
+    }
+}
+
+fn key_columns<'a>(df: &'a DataFrame, keys: &[&str], side: &str) -> Result<Vec<&'a Column>, String> {
+    keys.iter()
+        .map(|k| df.get(k).ok_or_else(|| format!("no column named '{}' in {} frame", k, side)))
+        .collect()
+}
+
+/// the formatted key values of row `i`
+fn fmt_key(cols: &[&Column], i: usize) -> Vec<String> {
+    cols.iter().map(|c| c.view().fmt_value(i)).collect()
+}
+
+/// map from key to row, failing on a repeated key
+fn key_index(cols: &[&Column], len: usize, side: &str) -> Result<HashMap<Vec<KeyVal>, usize>, String> {
+    let mut index = HashMap::new();
+    for i in 0..len {
+        let k: Vec<KeyVal> = cols.iter().map(|c| key_val(c, i)).collect();
+        if index.insert(k, i).is_some() {
+            return Err(format!("duplicate key [{}] in {} frame", fmt_key(cols, i).join(", "), side));
+        }
+    }
+    Ok(index)
+}
+
+/// compare the rows of `left` and `right` matched on the `keys` columns, which
+/// must be unique in each frame. Keys match by value, numeric keys of different
+/// types included, other key columns must have the same type on both sides.
+/// Values are compared under `opts`, its order settings are ignored.
+pub fn diff(left: &DataFrame, right: &DataFrame, keys: &[&str], opts: &CompareOptions) -> Result<FrameDiff, String> {
+    if keys.is_empty() {
+        return Err("diff needs at least one key column".to_string());
+    }
+    let lcols = key_columns(left, keys, "left")?;
+    let rcols = key_columns(right, keys, "right")?;
+    for ((k, a), b) in keys.iter().zip(&lcols).zip(&rcols) {
+        if !(a.same_type(b) || (a.is_numeric() && b.is_numeric())) {
+            return Err(format!("key column '{}' types differ: {} != {}", k, a.type_name(), b.type_name()));
+        }
+    }
+    let lindex = key_index(&lcols, left.len(), "left")?;
+    let rindex = key_index(&rcols, right.len(), "right")?;
+
+    let mut removed: Vec<usize> = lindex.iter().filter(|(k, _)| !rindex.contains_key(*k)).map(|(_, i)| *i).collect();
+    let mut added: Vec<usize> = rindex.iter().filter(|(k, _)| !lindex.contains_key(*k)).map(|(_, j)| *j).collect();
+    removed.sort_unstable();
+    added.sort_unstable();
+    let mut matched: Vec<(usize, usize)> = lindex.iter().filter_map(|(k, i)| Some((*i, *rindex.get(k)?))).collect();
+    matched.sort_unstable();
+    let common: Vec<&str> = left.names().filter(|n| !keys.contains(n) && right.get(n).is_some()).collect();
+    let mut changed = Vec::new();
+    for (i, j) in matched {
+        for n in &common {
+            let (a, b) = (left.get(n).unwrap(), right.get(n).unwrap());
+            if !cell_eq(a, i, b, j, opts) {
+                changed.push(CellChange {
+                    key: fmt_key(&lcols, i),
+                    column: n.to_string(),
+                    left: a.view().fmt_value(i),
+                    right: b.view().fmt_value(j),
+                });
+            }
+        }
+    }
+    Ok(FrameDiff {
+        added: right.take(&added),
+        removed: left.take(&removed),
+        changed,
+        added_columns: right.names().filter(|n| left.get(n).is_none()).map(String::from).collect(),
+        removed_columns: left.names().filter(|n| right.get(n).is_none()).map(String::from).collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+    use crate::nested::ListColumn;
+
+    #[test]
+    fn test_partial_eq() {
+        let a = df! { "x" => [1, 2], "y" => ["a", "b"] };
+        assert_eq!(a, df! { "x" => [1, 2], "y" => ["a", "b"] });
+        assert_ne!(a, df! { "y" => ["a", "b"], "x" => [1, 2] });
+        assert_ne!(a, df! { "x" => [1, 3], "y" => ["a", "b"] });
+        assert_ne!(Column::D(vec![f64::NAN]), Column::D(vec![f64::NAN]));
+        let l = ListColumn::from_lists(vec![vec![1, 2], vec![]]);
+        assert_eq!(Column::L(l.clone()), Column::L(l));
+    }
+
+    #[test]
+    fn test_frames_equal() {
+        let a = df! { "x" => [1.0, f64::NAN, 3.0], "k" => [1, 2, 3] };
+        let b = df! { "x" => [1.0, f64::NAN, 3.000001], "k" => [1, 2, 3] };
+        let opts = CompareOptions::default();
+        assert!(frames_equal(&a, &b, &opts));
+        assert_eq!(frame_mismatch(&a, &b, &CompareOptions::exact()).unwrap(), "column 'x' row 2: 3.0 != 3.000001");
+        assert!(!frames_equal(&a, &b, &CompareOptions { nan_equal: false, ..opts }));
+
+        let c = df! { "k" => [3, 1, 2], "x" => [3.0, 1.0, f64::NAN] };
+        assert!(!frames_equal(&a, &c, &opts));
+        assert!(frames_equal(&a, &c, &CompareOptions { check_row_order: false, check_column_order: false, ..opts }));
+
+        let f = df! { "x" => [1.0f32, f32::NAN, 3.0], "k" => [1.0, 2.0, 3.0] };
+        assert_eq!(frame_mismatch(&a, &f, &opts).unwrap(), "column 'x' types differ: f64 != f32");
+        assert!(frames_equal(&a, &f, &CompareOptions { check_dtype: false, ..opts }));
+    }
+
+    #[test]
+    #[should_panic(expected = "frames are not equal: row counts differ: 2 != 1")]
+    fn test_assert_frame_equal() {
+        assert_frame_equal(&df! { "x" => [1, 2] }, &df! { "x" => [1, 2] }, &CompareOptions::default());
+        assert_frame_equal(&df! { "x" => [1, 2] }, &df! { "x" => [1] }, &CompareOptions::default());
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = df! { "id" => [1, 2, 3], "px" => [1.0, 2.0, 3.0], "name" => ["a", "b", "c"], "old" => [0, 0, 0] };
+        let b = df! { "id" => [4, 3, 1], "px" => [4.0, 3.5, 1.0], "name" => ["d", "c", "z"], "new" => [1, 1, 1] };
+        let d = a.diff(&b, &["id"]).unwrap();
+        assert_eq!(d.added, df! { "id" => [4], "px" => [4.0], "name" => ["d"], "new" => [1] });
+        assert_eq!(d.removed, df! { "id" => [2], "px" => [2.0], "name" => ["b"], "old" => [0] });
+        let show: Vec<String> = d.changed.iter().map(|c| format!("{} {} {} {}", c.key[0], c.column, c.left, c.right)).collect();
+        assert_eq!(show, vec!["1 name a z", "3 px 3.0 3.5"]);
+        assert_eq!(d.added_columns, vec!["new"]);
+        assert_eq!(d.removed_columns, vec!["old"]);
+        assert!(d.to_string().contains("changed 2 cells:\n  [1] name: a -> z\n  [3] px: 3.0 -> 3.5"));
+
+        assert!(a.diff(&a, &["id"]).unwrap().is_empty());
+        assert!(a.diff(&df! { "id" => [1, 1] }, &["id"]).is_err());
+        assert!(a.diff(&b, &["missing"]).is_err());
+    }
+
+    #[test]
+    fn test_diff_key_types() {
+        // numeric keys match by value whatever their type
+        let a = df! { "id" => [1, 2], "v" => ["a", "b"] };
+        let b = df! { "id" => [2.0, 1.0, 3.5], "v" => ["b", "z", "c"] };
+        let d = a.diff(&b, &["id"]).unwrap();
+        assert!(d.removed.is_empty());
+        assert_eq!(d.added, df! { "id" => [3.5], "v" => ["c"] });
+        assert_eq!(d.changed, vec![CellChange { key: vec!["1".into()], column: "v".into(), left: "a".into(), right: "z".into() }]);
+        assert!(df! { "id" => [0.0] }.diff(&df! { "id" => [-0.0] }, &["id"]).unwrap().is_empty());
+
+        let e = a.diff(&df! { "id" => ["1", "2"], "v" => ["a", "b"] }, &["id"]).unwrap_err();
+        assert_eq!(e, "key column 'id' types differ: i32 != String");
+    }
+}
@@ -1,31 +1,246 @@
-//! A few examples of parallel processing single arrays by chunk
+//! A few examples of parallel processing single arrays by chunk, each chunk a
+//! job on the global `pool`. The kernels are generic over `Numeric`, and the
+//! `*_column` functions run them on any numeric `Column` variant. Vectorized
+//! float kernels are in `SimdFloat`.
 
-use std::ops::{Add, AddAssign};
-use std::thread;
+use crate::enum_df::Column;
+use crate::numeric::Numeric;
+use crate::pool;
+use crate::sketch::Sketch;
+
+mod simd;
+
+pub use simd::{BinOp, SimdFloat};
 
 pub fn process_array(arr: &mut [f32]) {
-    thread::scope(|scope| {
-        for (_ix, chunk) in arr.chunks_mut(3).enumerate() {
-            scope.spawn(move || {
+    process_array_t(arr)
+}
+
+pub fn process_array_t<T: Numeric>(arr: &mut [T]) {
+    pool::global().scope(|scope| {
+        for (ix, chunk) in arr.chunks_mut(3).enumerate() {
+            scope.spawn(move |_| {
                 for i in chunk.iter_mut() {
-                    *i += 1.0 + _ix as f32;
+                    *i += T::ONE + T::from_usize(ix);
                 }
             });
         }
     });
 }
 
-pub fn process_array_t<T>(arr: &mut [T])
+/// replace each element `x` by `f(x)`, `chunk_len` elements per job
+pub fn par_map<T, F>(arr: &mut [T], chunk_len: usize, f: F)
 where
-    T: From<i8> + Add<Output = T> + AddAssign + Send,
+    T: Numeric,
+    F: Fn(T) -> T + Sync,
 {
-    thread::scope(|scope| {
-        for (_ix, chunk) in arr.chunks_mut(3).enumerate() {
-            scope.spawn(move || {
-                for i in chunk.iter_mut() {
-                    *i += T::from(1) + T::from(_ix as i8);
-                }
-            });
+    pool::global().scope(|scope| {
+        for chunk in arr.chunks_mut(chunk_len.max(1)) {
+            let f = &f;
+            scope.spawn(move |_| chunk.iter_mut().for_each(|x| *x = f(*x)));
         }
     });
 }
+
+/// run `f` on each of `n` parts in its own job, results in part order
+fn par_parts<R, F>(n: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync,
+{
+    let mut out: Vec<Option<R>> = (0..n).map(|_| None).collect();
+    pool::global().scope(|scope| {
+        for (i, slot) in out.iter_mut().enumerate() {
+            let f = &f;
+            scope.spawn(move |_| *slot = Some(f(i)));
+        }
+    });
+    out.into_iter().map(|r| r.expect("every part has run")).collect()
+}
+
+/// run `f` on each chunk of `chunk_len` elements in its own job, results in
+/// chunk order
+fn par_chunks<T, R, F>(arr: &[T], chunk_len: usize, f: F) -> Vec<R>
+where
+    T: Numeric,
+    R: Send,
+    F: Fn(&[T]) -> R + Sync,
+{
+    let chunks: Vec<&[T]> = arr.chunks(chunk_len.max(1)).collect();
+    par_parts(chunks.len(), |i| f(chunks[i]))
+}
+
+/// sum of the elements skipping NaN, taken in f64 so integer sums cannot
+/// overflow
+pub fn par_sum<T: Numeric>(arr: &[T], chunk_len: usize) -> f64 {
+    par_chunks(arr, chunk_len, |c| c.iter().filter(|x| !x.is_nan()).map(|x| x.to_f64()).sum::<f64>())
+        .into_iter()
+        .sum()
+}
+
+fn min_max<T: Numeric>(a: Option<(T, T)>, b: Option<(T, T)>) -> Option<(T, T)> {
+    match (a, b) {
+        (Some((lo, hi)), Some((l, h))) => Some((if l < lo { l } else { lo }, if h > hi { h } else { hi })),
+        (a, b) => a.or(b),
+    }
+}
+
+/// smallest and largest elements skipping NaN, None if there are none
+pub fn par_min_max<T: Numeric>(arr: &[T], chunk_len: usize) -> Option<(T, T)> {
+    par_chunks(arr, chunk_len, |c| {
+        c.iter().filter(|x| !x.is_nan()).fold(None, |acc, x| min_max(acc, Some((*x, *x))))
+    })
+    .into_iter()
+    .fold(None, min_max)
+}
+
+/// evaluate `$e` with `$x` bound to the data of a numeric column, an error
+/// for other columns
+macro_rules! numeric_op {
+    ($c:expr, $x:ident => $e:expr) => {
+        match $c {
+            Column::F($x) => Ok($e),
+            Column::D($x) => Ok($e),
+            Column::I($x) => Ok($e),
+            c => Err(format!("expected a numeric column, not {}", c.type_name())),
+        }
+    };
+}
+
+/// `par_sum` of a numeric column
+pub fn sum_column(c: &Column, chunk_len: usize) -> Result<f64, String> {
+    numeric_op!(c, x => par_sum(x, chunk_len))
+}
+
+/// `par_min_max` of a numeric column, as f64
+pub fn min_max_column(c: &Column, chunk_len: usize) -> Result<Option<(f64, f64)>, String> {
+    numeric_op!(c, x => par_min_max(x, chunk_len).map(|(lo, hi)| (lo.to_f64(), hi.to_f64())))
+}
+
+/// multiply a numeric column by `factor` in place, integers saturating at
+/// their bounds
+pub fn scale_column(c: &mut Column, factor: f64, chunk_len: usize) -> Result<(), String> {
+    numeric_op!(c, x => par_map(x, chunk_len, |v| Numeric::from_f64(v.to_f64() * factor)))
+}
+
+/// evaluate `$e` with `$x` bound to the data of a float column, an error for
+/// other columns
+macro_rules! float_op {
+    ($c:expr, $x:ident => $e:expr) => {
+        match $c {
+            Column::F($x) => Ok($e),
+            Column::D($x) => Ok($e),
+            c => Err(format!("expected a float column, not {}", c.type_name())),
+        }
+    };
+}
+
+/// `SimdFloat::simd_sum` of a float column
+pub fn simd_sum_column(c: &Column) -> Result<f64, String> {
+    float_op!(c, x => SimdFloat::simd_sum(x).to_f64())
+}
+
+/// `SimdFloat::simd_min_max` of a float column, as f64
+pub fn simd_min_max_column(c: &Column) -> Result<Option<(f64, f64)>, String> {
+    float_op!(c, x => SimdFloat::simd_min_max(x).map(|(lo, hi)| (lo.to_f64(), hi.to_f64())))
+}
+
+/// dot product of two float columns of the same type
+pub fn dot_columns(a: &Column, b: &Column) -> Result<f64, String> {
+    match (a, b) {
+        (Column::F(x), Column::F(y)) => Ok(f32::simd_dot(x, y)?.to_f64()),
+        (Column::D(x), Column::D(y)) => f64::simd_dot(x, y),
+        _ => Err(format!("cannot take the dot product of {} and {}", a.type_name(), b.type_name())),
+    }
+}
+
+/// `op` applied to each pair of values of two float columns of the same type
+pub fn arith_columns(a: &Column, b: &Column, op: BinOp) -> Result<Column, String> {
+    match (a, b) {
+        (Column::F(x), Column::F(y)) => Ok(Column::F(f32::simd_zip(x, y, op)?)),
+        (Column::D(x), Column::D(y)) => Ok(Column::D(f64::simd_zip(x, y, op)?)),
+        _ => Err(format!("cannot combine {} and {}", a.type_name(), b.type_name())),
+    }
+}
+
+/// build a sketch of `col` by updating a fresh sketch per chunk of `chunk_len`
+/// rows, each in its own job, and merging them in order
+pub fn sketch_chunks<S, F>(col: &Column, chunk_len: usize, new: F) -> Result<S, String>
+where
+    S: Sketch + Send,
+    F: Fn() -> S + Sync,
+{
+    let chunk_len = chunk_len.max(1);
+    let parts = par_parts(col.len().div_ceil(chunk_len), |i| {
+        let mut s = new();
+        s.update(col.slice(i * chunk_len, chunk_len)).map(|_| s)
+    });
+    let mut out = new();
+    for s in parts {
+        out.merge(&s?)?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sketch::HyperLogLog;
+
+    #[test]
+    fn test_process_array() {
+        let mut a = [0.0f32; 5];
+        process_array(&mut a);
+        assert_eq!(a, [1.0, 1.0, 1.0, 2.0, 2.0]);
+
+        // more than 128 chunks, which used to wrap the chunk index
+        let mut b = vec![0u64; 600];
+        process_array_t(&mut b);
+        assert_eq!(b[599], 200);
+    }
+
+    #[test]
+    fn test_column_kernels() {
+        let mut c = Column::I(vec![4, -3, 7, 1, 0]);
+        assert_eq!(sum_column(&c, 2), Ok(9.0));
+        assert_eq!(min_max_column(&c, 2), Ok(Some((-3.0, 7.0))));
+        scale_column(&mut c, 1e9, 2).unwrap();
+        assert_eq!(c, Column::I(vec![i32::MAX, i32::MIN, i32::MAX, 1_000_000_000, 0]));
+
+        let d = Column::D(vec![f64::NAN, 2.5, -1.0]);
+        assert_eq!(sum_column(&d, 1), Ok(1.5));
+        assert_eq!(min_max_column(&d, 1), Ok(Some((-1.0, 2.5))));
+        assert_eq!(min_max_column(&Column::F(vec![f32::NAN]), 4), Ok(None));
+        assert!(sum_column(&Column::S(vec!["a".into()]), 4).is_err());
+        assert_eq!(par_min_max(&[3u32, 9, 1], 1), Some((1, 9)));
+    }
+
+    #[test]
+    fn test_simd_columns() {
+        let f = Column::F(vec![1.0, f32::NAN, 3.0]);
+        assert_eq!(simd_sum_column(&f), Ok(4.0));
+        assert_eq!(simd_min_max_column(&f), Ok(Some((1.0, 3.0))));
+        assert!(simd_sum_column(&Column::I(vec![1])).is_err());
+
+        let d = Column::D(vec![1.0, 2.0, 3.0]);
+        assert_eq!(dot_columns(&d, &d), Ok(14.0));
+        assert_eq!(arith_columns(&d, &d, BinOp::Mul), Ok(Column::D(vec![1.0, 4.0, 9.0])));
+        assert!(arith_columns(&d, &f, BinOp::Add).is_err());
+        assert!(dot_columns(&d, &Column::D(vec![1.0])).is_err());
+    }
+
+    #[test]
+    fn test_sketch_chunks() {
+        let c = Column::I((0..10_000).map(|i| i % 3000).collect());
+        let par = sketch_chunks(&c, 999, || HyperLogLog::new(12).unwrap()).unwrap();
+        let mut serial = HyperLogLog::new(12).unwrap();
+        serial.update(c.view()).unwrap();
+        assert_eq!(par.estimate(), serial.estimate());
+        let c = Column::irange(100_000);
+        let par = sketch_chunks(&c, 7001, || crate::sketch::TDigest::new(100.0)).unwrap();
+        assert_eq!(par.count(), 100_000);
+        assert_eq!(par.quantile(0.0), 0.0);
+        assert_eq!(par.quantile(1.0), 99_999.0);
+        assert!(sketch_chunks(&Column::S(vec!["a".into()]), 10, || crate::sketch::TDigest::new(100.0)).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! Vectorized float kernels, using AVX when the cpu has it (checked at run
+//! time) and a scalar fallback otherwise.
+//!
+//! The fallback keeps one accumulator per AVX lane and combines them in the
+//! same order as the vector code, so both paths give bit for bit the same
+//! results. Sums and min/max skip NaN, `dot` and the element-wise arithmetic
+//! do not.
+
+use crate::numeric::Numeric;
+
+/// Element-wise arithmetic operation, see `SimdFloat::simd_zip`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn apply<T: Numeric>(self, a: T, b: T) -> T {
+        match self {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Div => a / b,
+        }
+    }
+}
+
+/// The float types with vectorized kernels
+pub trait SimdFloat: Numeric {
+    /// values per AVX register
+    const LANES: usize;
+    const INFINITY: Self;
+    const NEG_INFINITY: Self;
+
+    /// sum of the values, skipping NaN
+    fn simd_sum(x: &[Self]) -> Self;
+    /// smallest and largest values skipping NaN, None if there are none
+    fn simd_min_max(x: &[Self]) -> Option<(Self, Self)>;
+    fn simd_dot(a: &[Self], b: &[Self]) -> Result<Self, String>;
+    /// `op` applied to each pair of values
+    fn simd_zip(a: &[Self], b: &[Self], op: BinOp) -> Result<Vec<Self>, String>;
+}
+
+fn check_lengths(a: usize, b: usize) -> Result<(), String> {
+    if a != b {
+        return Err(format!("cannot combine arrays of length {} and {}", a, b));
+    }
+    Ok(())
+}
+
+// the lane and tail steps shared by both paths, written to match the
+// semantics of the AVX instructions exactly
+
+fn masked_add<T: Numeric>(acc: T, v: T) -> T {
+    acc + if v.is_nan() { T::ZERO } else { v }
+}
+
+// `_mm256_min_ps(a, b)` is `a < b ? a : b`, likewise for max
+fn lane_min<T: SimdFloat>(lo: T, v: T) -> T {
+    let v = if v.is_nan() { T::INFINITY } else { v };
+    if lo < v { lo } else { v }
+}
+
+fn lane_max<T: SimdFloat>(hi: T, v: T) -> T {
+    let v = if v.is_nan() { T::NEG_INFINITY } else { v };
+    if hi > v { hi } else { v }
+}
+
+fn combine<T: Copy>(lanes: &[T], f: impl Fn(T, T) -> T) -> T {
+    lanes[1..].iter().fold(lanes[0], |a, v| f(a, *v))
+}
+
+fn finish_sum<T: Numeric>(lanes: &[T], tail: &[T]) -> T {
+    tail.iter().fold(combine(lanes, |a, b| a + b), |a, v| masked_add(a, *v))
+}
+
+fn finish_min_max<T: SimdFloat>(lo: &[T], hi: &[T], tail: &[T]) -> Option<(T, T)> {
+    let lo = tail.iter().fold(combine(lo, lane_min), |a, v| lane_min(a, *v));
+    let hi = tail.iter().fold(combine(hi, lane_max), |a, v| lane_max(a, *v));
+    // only values that were all NaN (or no values) leave lo above hi
+    (lo <= hi).then_some((lo, hi))
+}
+
+fn finish_dot<T: Numeric>(lanes: &[T], a: &[T], b: &[T]) -> T {
+    a.iter().zip(b).fold(combine(lanes, |x, y| x + y), |acc, (x, y)| acc + *x * *y)
+}
+
+/// the kernels one lane at a time
+mod scalar {
+    use super::*;
+
+    fn lanes<T: SimdFloat>(x: &[T], init: T, f: impl Fn(T, T) -> T) -> (Vec<T>, &[T]) {
+        let mut acc = vec![init; T::LANES];
+        let chunks = x.chunks_exact(T::LANES);
+        let tail = chunks.remainder();
+        for c in chunks {
+            acc.iter_mut().zip(c).for_each(|(a, v)| *a = f(*a, *v));
+        }
+        (acc, tail)
+    }
+
+    pub fn sum<T: SimdFloat>(x: &[T]) -> T {
+        let (acc, tail) = lanes(x, T::ZERO, masked_add);
+        finish_sum(&acc, tail)
+    }
+
+    pub fn min_max<T: SimdFloat>(x: &[T]) -> Option<(T, T)> {
+        let (lo, tail) = lanes(x, T::INFINITY, lane_min);
+        let (hi, _) = lanes(x, T::NEG_INFINITY, lane_max);
+        finish_min_max(&lo, &hi, tail)
+    }
+
+    pub fn dot<T: SimdFloat>(a: &[T], b: &[T]) -> T {
+        let mut acc = vec![T::ZERO; T::LANES];
+        let n = a.len() / T::LANES * T::LANES;
+        for (ca, cb) in a[..n].chunks_exact(T::LANES).zip(b[..n].chunks_exact(T::LANES)) {
+            for ((acc, x), y) in acc.iter_mut().zip(ca).zip(cb) {
+                *acc += *x * *y;
+            }
+        }
+        finish_dot(&acc, &a[n..], &b[n..])
+    }
+
+    pub fn zip<T: SimdFloat>(a: &[T], b: &[T], op: BinOp) -> Vec<T> {
+        a.iter().zip(b).map(|(x, y)| op.apply(*x, *y)).collect()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+macro_rules! avx_kernels {
+    ($m:ident, $t:ty, $lanes:literal, $load:ident, $store:ident, $zero:ident, $set1:ident, $add:ident,
+     $sub:ident, $mul:ident, $div:ident, $min:ident, $max:ident, $cmp:ident, $and:ident, $blend:ident) => {
+        /// the kernels on AVX registers, only to be called when the cpu has AVX
+        mod $m {
+            use std::arch::x86_64::*;
+
+            use super::*;
+
+            #[target_feature(enable = "avx")]
+            pub unsafe fn sum(x: &[$t]) -> $t {
+                let mut acc = $zero();
+                let chunks = x.chunks_exact($lanes);
+                let tail = chunks.remainder();
+                for c in chunks {
+                    let v = $load(c.as_ptr());
+                    // NaN lanes are masked to +0.0
+                    acc = $add(acc, $and($cmp::<_CMP_ORD_Q>(v, v), v));
+                }
+                let mut lanes = [0.0; $lanes];
+                $store(lanes.as_mut_ptr(), acc);
+                finish_sum(&lanes, tail)
+            }
+
+            #[target_feature(enable = "avx")]
+            pub unsafe fn min_max(x: &[$t]) -> Option<($t, $t)> {
+                let (inf, neg_inf) = ($set1(<$t>::INFINITY), $set1(<$t>::NEG_INFINITY));
+                let (mut lo, mut hi) = (inf, neg_inf);
+                let chunks = x.chunks_exact($lanes);
+                let tail = chunks.remainder();
+                for c in chunks {
+                    let v = $load(c.as_ptr());
+                    let nan = $cmp::<_CMP_UNORD_Q>(v, v);
+                    lo = $min(lo, $blend(v, inf, nan));
+                    hi = $max(hi, $blend(v, neg_inf, nan));
+                }
+                let (mut l, mut h) = ([0.0; $lanes], [0.0; $lanes]);
+                $store(l.as_mut_ptr(), lo);
+                $store(h.as_mut_ptr(), hi);
+                finish_min_max(&l, &h, tail)
+            }
+
+            #[target_feature(enable = "avx")]
+            pub unsafe fn dot(a: &[$t], b: &[$t]) -> $t {
+                let mut acc = $zero();
+                let n = a.len() / $lanes * $lanes;
+                for i in (0..n).step_by($lanes) {
+                    acc = $add(acc, $mul($load(a.as_ptr().add(i)), $load(b.as_ptr().add(i))));
+                }
+                let mut lanes = [0.0; $lanes];
+                $store(lanes.as_mut_ptr(), acc);
+                finish_dot(&lanes, &a[n..], &b[n..])
+            }
+
+            #[target_feature(enable = "avx")]
+            pub unsafe fn zip(a: &[$t], b: &[$t], op: BinOp) -> Vec<$t> {
+                let mut out = vec![0.0; a.len()];
+                let n = a.len() / $lanes * $lanes;
+                for i in (0..n).step_by($lanes) {
+                    let (x, y) = ($load(a.as_ptr().add(i)), $load(b.as_ptr().add(i)));
+                    let r = match op {
+                        BinOp::Add => $add(x, y),
+                        BinOp::Sub => $sub(x, y),
+                        BinOp::Mul => $mul(x, y),
+                        BinOp::Div => $div(x, y),
+                    };
+                    $store(out.as_mut_ptr().add(i), r);
+                }
+                for ((o, x), y) in out[n..].iter_mut().zip(&a[n..]).zip(&b[n..]) {
+                    *o = op.apply(*x, *y);
+                }
+                out
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+avx_kernels!(avx_f32, f32, 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_setzero_ps, _mm256_set1_ps, _mm256_add_ps,
+    _mm256_sub_ps, _mm256_mul_ps, _mm256_div_ps, _mm256_min_ps, _mm256_max_ps, _mm256_cmp_ps, _mm256_and_ps,
+    _mm256_blendv_ps);
+#[cfg(target_arch = "x86_64")]
+avx_kernels!(avx_f64, f64, 4, _mm256_loadu_pd, _mm256_storeu_pd, _mm256_setzero_pd, _mm256_set1_pd, _mm256_add_pd,
+    _mm256_sub_pd, _mm256_mul_pd, _mm256_div_pd, _mm256_min_pd, _mm256_max_pd, _mm256_cmp_pd, _mm256_and_pd,
+    _mm256_blendv_pd);
+
+/// run the AVX kernel `$avx` if the cpu has AVX, `$scalar` otherwise
+macro_rules! dispatch {
+    ($avx:expr, $scalar:expr) => {{
+        // safety: the AVX kernels are only called once AVX support is
+        // detected, and only read within their slices
+        #[cfg(target_arch = "x86_64")]
+        let r = if is_x86_feature_detected!("avx") { unsafe { $avx } } else { $scalar };
+        #[cfg(not(target_arch = "x86_64"))]
+        let r = $scalar;
+        r
+    }};
+}
+
+macro_rules! simd_float {
+    ($t:ty, $lanes:literal, $m:ident) => {
+        impl SimdFloat for $t {
+            const LANES: usize = $lanes;
+            const INFINITY: Self = <$t>::INFINITY;
+            const NEG_INFINITY: Self = <$t>::NEG_INFINITY;
+
+            fn simd_sum(x: &[Self]) -> Self {
+                dispatch!($m::sum(x), scalar::sum(x))
+            }
+
+            fn simd_min_max(x: &[Self]) -> Option<(Self, Self)> {
+                dispatch!($m::min_max(x), scalar::min_max(x))
+            }
+
+            fn simd_dot(a: &[Self], b: &[Self]) -> Result<Self, String> {
+                check_lengths(a.len(), b.len())?;
+                Ok(dispatch!($m::dot(a, b), scalar::dot(a, b)))
+            }
+
+            fn simd_zip(a: &[Self], b: &[Self], op: BinOp) -> Result<Vec<Self>, String> {
+                check_lengths(a.len(), b.len())?;
+                Ok(dispatch!($m::zip(a, b, op), scalar::zip(a, b, op)))
+            }
+        }
+    };
+}
+
+simd_float!(f32, 8, avx_f32);
+simd_float!(f64, 4, avx_f64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// awkward lengths and values, with NaN, signed zeros and infinities
+    fn data<T: SimdFloat>(n: usize, seed: u64) -> Vec<T> {
+        let mut s = seed;
+        (0..n).map(|i| {
+            s = s.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            match i % 29 {
+                3 => T::from_f64(f64::NAN),
+                7 => T::from_f64(-0.0),
+                _ => T::from_f64((s >> 11) as f64 / (1u64 << 53) as f64 * 200.0 - 100.0),
+            }
+        }).collect()
+    }
+
+    fn bits<T: SimdFloat>(v: T) -> u64 {
+        v.to_f64().to_bits()
+    }
+
+    fn check_matches_scalar<T: SimdFloat>() {
+        for n in [0, 1, 3, T::LANES, T::LANES + 1, 29, 100, 1001] {
+            let (a, b) = (data::<T>(n, 1), data::<T>(n, 2));
+            assert_eq!(bits(T::simd_sum(&a)), bits(scalar::sum(&a)), "sum of {}", n);
+            assert_eq!(T::simd_min_max(&a).map(|(l, h)| (bits(l), bits(h))),
+                scalar::min_max(&a).map(|(l, h)| (bits(l), bits(h))), "min_max of {}", n);
+            let (d, sd) = (T::simd_dot(&a, &b).unwrap(), scalar::dot(&a, &b));
+            assert!(bits(d) == bits(sd) || (d.is_nan() && sd.is_nan()), "dot of {}", n);
+            for op in [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div] {
+                let (v, s) = (T::simd_zip(&a, &b, op).unwrap(), scalar::zip(&a, &b, op));
+                assert!(v.iter().zip(&s).all(|(x, y)| bits(*x) == bits(*y) || (x.is_nan() && y.is_nan())));
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_scalar() {
+        check_matches_scalar::<f32>();
+        check_matches_scalar::<f64>();
+    }
+
+    #[test]
+    fn test_kernels() {
+        let x: Vec<f64> = (1..=10).map(|i| i as f64).chain([f64::NAN]).collect();
+        assert_eq!(f64::simd_sum(&x), 55.0);
+        assert_eq!(f64::simd_min_max(&x), Some((1.0, 10.0)));
+        assert_eq!(f32::simd_min_max(&[f32::NAN; 9]), None);
+        assert_eq!(f32::simd_min_max(&[]), None);
+        let y = vec![2.0; 10];
+        assert_eq!(f64::simd_dot(&x[..10], &y), Ok(110.0));
+        assert!(f64::simd_dot(&x, &y).is_err());
+        assert_eq!(f32::simd_zip(&[1.0, 2.0, 3.0], &[4.0, 4.0, 4.0], BinOp::Div).unwrap(), vec![0.25, 0.5, 0.75]);
+        assert_eq!(f64::simd_zip(&y, &x[..10], BinOp::Sub).unwrap()[9], -8.0);
+    }
+}
@@ -0,0 +1,405 @@
+//! CSV reading and writing for `enum_df` frames.
+//!
+//! `read_batches` streams a file as a sequence of `DataFrame`s of at most
+//! `batch_size` rows, so files larger than memory can be processed a batch at
+//! a time (see `groupby::GroupBy` for aggregating over the stream). Column
+//! types are inferred once from the first `infer_rows` records and every batch
+//! is parsed with that schema: integers as `Column::I`, other numbers as
+//! `Column::D` and anything else as `Column::S`.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use crate::chunked::ChunkedFrame;
+use crate::enum_df::{Column, DataFrame};
+
+/// How a CSV stream is read. A header naming the same column twice is an
+/// error.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub has_header: bool,
+    pub batch_size: usize,
+    /// records sampled for the column types. The types are not widened
+    /// afterwards: a later value that does not parse as its column's type
+    /// ends the stream with an error naming its line and column, so raise
+    /// this for files whose first records are not representative.
+    pub infer_rows: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: b',', has_header: true, batch_size: 8192, infer_rows: 1000 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    I,
+    D,
+    S,
+}
+
+impl Kind {
+    fn infer<'a>(mut values: impl Iterator<Item = &'a str>) -> Kind {
+        let mut kind = Kind::I;
+        values.try_for_each(|v| {
+            let v = v.trim();
+            if kind == Kind::I && v.parse::<i32>().is_err() {
+                kind = Kind::D;
+            }
+            if kind == Kind::D && v.parse::<f64>().is_err() {
+                kind = Kind::S;
+                return None;
+            }
+            Some(())
+        });
+        kind
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Kind::I => "i32",
+            Kind::D => "f64",
+            Kind::S => "String",
+        }
+    }
+
+    fn empty(&self) -> Column {
+        match self {
+            Kind::I => Column::I(Vec::new()),
+            Kind::D => Column::D(Vec::new()),
+            Kind::S => Column::S(Vec::new()),
+        }
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// split one record, None if a quoted field is still open at the end of `s`
+fn split_record(s: &str, delimiter: char) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if !in_quotes => break,
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => (),
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+/// Iterator over batches of a CSV stream, created by `read_batches`
+pub struct CsvBatches<R> {
+    reader: R,
+    opts: CsvOptions,
+    names: Vec<String>,
+    kinds: Vec<Kind>,
+    // records read ahead for schema inference, with their line numbers
+    pending: VecDeque<(usize, Vec<String>)>,
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> CsvBatches<R> {
+    /// next record and the line it started on, skipping blank lines
+    fn read_record(&mut self) -> io::Result<Option<(usize, Vec<String>)>> {
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            if self.reader.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if !buf.trim_end_matches(['\r', '\n']).is_empty() {
+                break;
+            }
+        }
+        let start = self.line;
+        loop {
+            if let Some(fields) = split_record(&buf, self.opts.delimiter as char) {
+                return Ok(Some((start, fields)));
+            }
+            if self.reader.read_line(&mut buf)? == 0 {
+                return Err(invalid(format!("line {}: unterminated quoted field", start)));
+            }
+            self.line += 1;
+        }
+    }
+
+    /// (name, element type) of each column, fixed for the whole stream
+    pub fn schema(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        self.names.iter().map(String::as_str).zip(self.kinds.iter().map(Kind::type_name))
+    }
+
+    /// an empty frame with this stream's schema
+    pub fn empty_frame(&self) -> DataFrame {
+        let mut df = DataFrame::new();
+        for (n, k) in self.names.iter().zip(&self.kinds) {
+            df.addcol(n, k.empty()).expect("empty columns have equal length");
+        }
+        df
+    }
+
+    fn next_batch(&mut self) -> io::Result<Option<DataFrame>> {
+        let mut cols: Vec<Column> = self.kinds.iter().map(Kind::empty).collect();
+        let mut n = 0;
+        while n < self.opts.batch_size {
+            let rec = match self.pending.pop_front() {
+                Some(r) => r,
+                None => match self.read_record()? {
+                    Some(r) => r,
+                    None => break,
+                },
+            };
+            let (line, fields) = rec;
+            if fields.len() != self.names.len() {
+                return Err(invalid(format!(
+                    "line {}: expected {} fields, found {}", line, self.names.len(), fields.len()
+                )));
+            }
+            for ((col, v), name) in cols.iter_mut().zip(fields).zip(&self.names) {
+                let t = col.type_name();
+                let bad = || invalid(format!("line {}: column '{}': cannot parse '{}' as {}", line, name, v, t));
+                match col {
+                    Column::I(x) => x.push(v.trim().parse().map_err(|_| bad())?),
+                    Column::D(x) => x.push(v.trim().parse().map_err(|_| bad())?),
+                    Column::S(x) => x.push(v),
+                    _ => unreachable!("csv columns are only i32, f64 or String"),
+                }
+            }
+            n += 1;
+        }
+        if n == 0 {
+            return Ok(None);
+        }
+        let mut df = DataFrame::new();
+        for (name, c) in self.names.iter().zip(cols) {
+            df.addcol(name, c).expect("every record has all fields");
+        }
+        Ok(Some(df))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvBatches<R> {
+    type Item = io::Result<DataFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let r = self.next_batch().transpose();
+        // stop after the end of input or the first error
+        self.done = !matches!(r, Some(Ok(_)));
+        r
+    }
+}
+
+/// Read the header and the schema inference sample, returning an iterator
+/// over the remaining stream in batches
+pub fn read_batches<R: BufRead>(reader: R, opts: CsvOptions) -> io::Result<CsvBatches<R>> {
+    assert!(opts.batch_size > 0, "batch size must be non-zero");
+    let mut b = CsvBatches {
+        reader,
+        opts,
+        names: Vec::new(),
+        kinds: Vec::new(),
+        pending: VecDeque::new(),
+        line: 0,
+        done: false,
+    };
+    let first = b.read_record()?;
+    let Some((_, first)) = first else {
+        return Ok(b);
+    };
+    if b.opts.has_header {
+        if let Some(n) = first.iter().enumerate().find(|(i, n)| first[..*i].contains(n)).map(|(_, n)| n) {
+            return Err(invalid(format!("line {}: duplicate column '{}' in the header", b.line, n)));
+        }
+        b.names = first;
+    } else {
+        b.names = (0..first.len()).map(|i| format!("column_{}", i)).collect();
+        b.pending.push_back((b.line, first));
+    }
+    while b.pending.len() < b.opts.infer_rows {
+        match b.read_record()? {
+            Some(r) => b.pending.push_back(r),
+            None => break,
+        }
+    }
+    b.kinds = (0..b.names.len())
+        .map(|i| Kind::infer(b.pending.iter().filter_map(|(_, r)| r.get(i).map(String::as_str))))
+        .collect();
+    Ok(b)
+}
+
+/// Read a whole CSV stream into one frame
+pub fn read_csv<R: BufRead>(reader: R, opts: CsvOptions) -> io::Result<DataFrame> {
+    let batches = read_batches(reader, opts)?;
+    let empty = batches.empty_frame();
+    let mut frame = ChunkedFrame::new();
+    for batch in batches {
+        frame.append(batch?).map_err(|e| invalid(e.to_string()))?;
+    }
+    if frame.is_empty() {
+        return Ok(empty);
+    }
+    Ok(frame.into_frame())
+}
+
+fn write_field<W: Write>(w: &mut W, v: &str, delimiter: u8) -> io::Result<()> {
+    let needs_quotes = v.bytes().any(|b| b == delimiter || b == b'"' || b == b'\n' || b == b'\r');
+    if needs_quotes {
+        write!(w, "\"{}\"", v.replace('"', "\"\""))
+    } else {
+        w.write_all(v.as_bytes())
+    }
+}
+
+/// Write a frame as CSV with a header row
+pub fn write_csv<W: Write>(df: &DataFrame, mut w: W, delimiter: u8) -> io::Result<()> {
+    let cols: Vec<(&str, &Column)> = df.iter().collect();
+    for (i, (name, _)) in cols.iter().enumerate() {
+        if i > 0 {
+            w.write_all(&[delimiter])?;
+        }
+        write_field(&mut w, name, delimiter)?;
+    }
+    w.write_all(b"\n")?;
+    for row in 0..df.len() {
+        for (i, (_, c)) in cols.iter().enumerate() {
+            if i > 0 {
+                w.write_all(&[delimiter])?;
+            }
+            match c {
+                // with a decimal point, so floats read back as floats
+                Column::F(x) => write!(w, "{:?}", x[row])?,
+                Column::D(x) => write!(w, "{:?}", x[row])?,
+                Column::I(x) => write!(w, "{}", x[row])?,
+                Column::S(x) => write_field(&mut w, &x[row], delimiter)?,
+                // nested values are written in their display form
+                c => write_field(&mut w, &c.view().fmt_value(row), delimiter)?,
+            }
+        }
+        w.write_all(b"\n")?;
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+    use crate::groupby::{group_by, Agg, GroupBy};
+
+    const DATA: &str = "k,v,name\na,1,x\nb,2.5,\"y, z\"\na,3,\"say \"\"hi\"\"\"\n\nc,4,\"multi\nline\"\n";
+
+    #[test]
+    fn test_split_record() {
+        assert_eq!(split_record("a,b\n", ','), Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(split_record("a,\"b,c\"\r\n", ','), Some(vec!["a".to_string(), "b,c".to_string()]));
+        assert_eq!(split_record("a,\"b\n", ','), None);
+        assert_eq!(split_record("a;b", ';'), Some(vec!["a".to_string(), "b".to_string()]));
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let df = read_csv(DATA.as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(df.len(), 4);
+        assert_eq!(df.names().collect::<Vec<_>>(), vec!["k", "v", "name"]);
+        let Some(Column::D(v)) = df.get("v") else { panic!("expected D column") };
+        assert_eq!(v, &vec![1.0, 2.5, 3.0, 4.0]);
+        let Some(Column::S(n)) = df.get("name") else { panic!("expected S column") };
+        assert_eq!(n, &vec!["x", "y, z", "say \"hi\"", "multi\nline"]);
+    }
+
+    #[test]
+    fn test_stable_schema() {
+        let opts = CsvOptions { batch_size: 2, infer_rows: 1, ..CsvOptions::default() };
+        let mut batches = read_batches(DATA.as_bytes(), opts).unwrap();
+        assert_eq!(batches.schema().collect::<Vec<_>>(), vec![("k", "String"), ("v", "i32"), ("name", "String")]);
+        // the schema comes from the first record only, so the float in the
+        // second record is an error rather than a change of type
+        let err = batches.next().unwrap().err().unwrap();
+        assert_eq!(err.to_string(), "line 3: column 'v': cannot parse '2.5' as i32");
+        assert!(batches.next().is_none());
+    }
+
+    #[test]
+    fn test_batches() {
+        let opts = CsvOptions { batch_size: 3, has_header: false, ..CsvOptions::default() };
+        let data = "1,2\n3,4\n5,6\n7,8\n";
+        let lens: Vec<usize> = read_batches(data.as_bytes(), opts).unwrap()
+            .map(|b| b.unwrap().len()).collect();
+        assert_eq!(lens, vec![3, 1]);
+
+        let bad = "a,b\n1,2\n3\n";
+        let r = read_csv(bad.as_bytes(), CsvOptions::default());
+        assert_eq!(r.err().unwrap().to_string(), "line 3: expected 2 fields, found 1");
+        let r = read_csv("a,b,a\n1,2,3\n".as_bytes(), CsvOptions::default());
+        assert_eq!(r.err().unwrap().to_string(), "line 1: duplicate column 'a' in the header");
+    }
+
+    #[test]
+    fn test_streaming_group_by() {
+        let data = "k,v\na,1\nb,2\na,3\nc,4\nb,5\n";
+        let opts = CsvOptions { batch_size: 2, ..CsvOptions::default() };
+        let mut g = GroupBy::new(&["k"], &[("v", Agg::Sum)]).unwrap();
+        for batch in read_batches(data.as_bytes(), opts).unwrap() {
+            g.update(&batch.unwrap()).unwrap();
+        }
+        let streamed = g.finish();
+
+        let df = read_csv(data.as_bytes(), CsvOptions::default()).unwrap();
+        let whole = group_by(&df, &["k"], &[("v", Agg::Sum)]).unwrap();
+        let (Some(Column::D(a)), Some(Column::D(b))) = (streamed.get("v_sum"), whole.get("v_sum")) else {
+            panic!("expected D columns")
+        };
+        assert_eq!(a, b);
+        assert_eq!(a, &vec![4.0, 7.0, 4.0]);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let df = df! { "a" => [1, 2], "b" => ["x", "y,\"z\""] };
+        let mut out = Vec::new();
+        write_csv(&df, &mut out, b',').unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "a,b\n1,x\n2,\"y,\"\"z\"\"\"\n");
+        let back = read_csv(out.as_slice(), CsvOptions::default()).unwrap();
+        let Some(Column::S(b)) = back.get("b") else { panic!("expected S column") };
+        assert_eq!(b[1], "y,\"z\"");
+
+        let df = df! { "d" => [1.0, 2.5, f64::NAN], "i" => [1, 2, 3] };
+        let mut out = Vec::new();
+        write_csv(&df, &mut out, b',').unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "d,i\n1.0,1\n2.5,2\nNaN,3\n");
+        let back = read_csv(out.as_slice(), CsvOptions::default()).unwrap();
+        assert_eq!(back.get("d").unwrap().type_name(), "f64");
+        assert_eq!(back.get("i").unwrap().type_name(), "i32");
+    }
+
+    #[test]
+    fn test_empty() {
+        let df = read_csv("a,b\n".as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(df.len(), 0);
+        assert_eq!(df.names().count(), 2);
+        let df = read_csv("".as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(df.names().count(), 0);
+    }
+}
@@ -0,0 +1,149 @@
+//! Aggregations over numeric `dyn_df` columns.
+//!
+//! `aggregate` finds the element type of a column among the numeric builtins
+//! (`i32`, `i64`, `u32`, `u64`, `f32`, `f64`) so callers need not name it.
+//! Sums of integers are taken in `i64` or `u64` and fail on overflow, `min`
+//! and `max` keep the column's type, and `mean` is always `f64`. NaN is
+//! skipped as missing, so `count` is the number of non-NaN values.
+
+use std::fmt;
+
+use crate::dyn_df::{Col, Column};
+use crate::groupby::Agg;
+
+/// A single value of one of the numeric element types
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scalar {
+    I32(i32),
+    I64(i64),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Scalar {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Scalar::I32(x) => x as f64,
+            Scalar::I64(x) => x as f64,
+            Scalar::U32(x) => x as f64,
+            Scalar::U64(x) => x as f64,
+            Scalar::F32(x) => x as f64,
+            Scalar::F64(x) => x,
+        }
+    }
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::I32(x) => write!(f, "{}", x),
+            Scalar::I64(x) => write!(f, "{}", x),
+            Scalar::U32(x) => write!(f, "{}", x),
+            Scalar::U64(x) => write!(f, "{}", x),
+            Scalar::F32(x) => write!(f, "{:?}", x),
+            Scalar::F64(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+fn no_values(agg: Agg) -> String {
+    format!("cannot take the {} of a column without values", agg.name())
+}
+
+fn ints<T>(v: &[T], agg: Agg, wrap: fn(T) -> Scalar, signed: bool) -> Result<Scalar, String>
+where
+    T: Copy + Ord + Into<i128>,
+{
+    // i128 holds any sum of up to 2^64 i64 or u64 values
+    let sum = || v.iter().map(|x| (*x).into()).sum::<i128>();
+    match agg {
+        Agg::Count => Ok(Scalar::U64(v.len() as u64)),
+        Agg::Sum if signed => i64::try_from(sum()).map(Scalar::I64).map_err(|_| "sum overflows i64".to_string()),
+        Agg::Sum => u64::try_from(sum()).map(Scalar::U64).map_err(|_| "sum overflows u64".to_string()),
+        Agg::Mean => Ok(Scalar::F64(sum() as f64 / v.len() as f64)),
+        Agg::Min => v.iter().min().map(|x| wrap(*x)).ok_or_else(|| no_values(agg)),
+        Agg::Max => v.iter().max().map(|x| wrap(*x)).ok_or_else(|| no_values(agg)),
+    }
+}
+
+fn floats<T>(v: &[T], agg: Agg, wrap: fn(T) -> Scalar) -> Result<Scalar, String>
+where
+    T: Copy + PartialOrd + Into<f64>,
+{
+    let vals = || v.iter().copied().filter(|x| !(*x).into().is_nan());
+    let sum = || vals().map(Into::into).sum::<f64>();
+    match agg {
+        Agg::Count => Ok(Scalar::U64(vals().count() as u64)),
+        Agg::Sum => Ok(Scalar::F64(sum())),
+        Agg::Mean => Ok(Scalar::F64(sum() / vals().count() as f64)),
+        Agg::Min => vals().reduce(|a, b| if b < a { b } else { a }).map(wrap).ok_or_else(|| no_values(agg)),
+        Agg::Max => vals().reduce(|a, b| if b > a { b } else { a }).map(wrap).ok_or_else(|| no_values(agg)),
+    }
+}
+
+fn slice<T: 'static>(c: &dyn Column) -> Option<&[T]> {
+    c.as_any().downcast_ref::<Col<T>>().map(Col::as_slice)
+}
+
+/// `agg` of a column of any numeric element type
+pub fn aggregate(c: &dyn Column, agg: Agg) -> Result<Scalar, String> {
+    if let Some(v) = slice::<i32>(c) {
+        ints(v, agg, Scalar::I32, true)
+    } else if let Some(v) = slice::<i64>(c) {
+        ints(v, agg, Scalar::I64, true)
+    } else if let Some(v) = slice::<u32>(c) {
+        ints(v, agg, Scalar::U32, false)
+    } else if let Some(v) = slice::<u64>(c) {
+        ints(v, agg, Scalar::U64, false)
+    } else if let Some(v) = slice::<f32>(c) {
+        floats(v, agg, Scalar::F32)
+    } else if let Some(v) = slice::<f64>(c) {
+        floats(v, agg, Scalar::F64)
+    } else {
+        Err(format!("cannot aggregate a column of type {}", c.type_name()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dyn_df::DataFrame;
+
+    #[test]
+    fn test_ints() {
+        let c = Col::from_vec(vec![3, -1, 4]);
+        assert_eq!(aggregate(&c, Agg::Sum), Ok(Scalar::I64(6)));
+        assert_eq!(aggregate(&c, Agg::Mean), Ok(Scalar::F64(2.0)));
+        assert_eq!(aggregate(&c, Agg::Min), Ok(Scalar::I32(-1)));
+        assert_eq!(aggregate(&c, Agg::Max), Ok(Scalar::I32(4)));
+        assert_eq!(aggregate(&c, Agg::Count), Ok(Scalar::U64(3)));
+        assert_eq!(aggregate(&Col::from_vec(vec![u32::MAX, 1]), Agg::Sum), Ok(Scalar::U64(u32::MAX as u64 + 1)));
+        assert!(aggregate(&Col::from_vec(vec![u64::MAX, 1]), Agg::Sum).is_err());
+        assert!(aggregate(&Col::<i64>::new(), Agg::Min).is_err());
+    }
+
+    #[test]
+    fn test_floats() {
+        let c = Col::from_vec(vec![1.5f32, f32::NAN, -0.5]);
+        assert_eq!(aggregate(&c, Agg::Count), Ok(Scalar::U64(2)));
+        assert_eq!(aggregate(&c, Agg::Sum), Ok(Scalar::F64(1.0)));
+        assert_eq!(aggregate(&c, Agg::Min), Ok(Scalar::F32(-0.5)));
+        assert_eq!(aggregate(&c, Agg::Max).unwrap().to_string(), "1.5");
+        assert!(aggregate(&Col::from_vec(vec![f64::NAN]), Agg::Max).is_err());
+        assert!(aggregate(&Col::from_vec(vec![f64::NAN]), Agg::Mean).unwrap().as_f64().is_nan());
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut df = DataFrame::new();
+        df.add_col("n".to_string(), Col::from_vec(vec![1u64, 2, 3, 4]));
+        df.add_col("s".to_string(), Col::from_vec(vec!["a".to_string(); 4]));
+        assert_eq!(df.sum("n"), Ok(Scalar::U64(10)));
+        assert_eq!(df.mean("n"), Ok(Scalar::F64(2.5)));
+        assert_eq!(df.max("n").unwrap().as_f64(), 4.0);
+        assert!(df.sum("s").is_err());
+        assert!(df.count("missing").is_err());
+    }
+}
@@ -1,12 +1,45 @@
-use std::{any::Any, collections::HashMap, slice::Iter, sync::Arc};
+use std::{any::Any, cmp::Ordering, collections::HashMap, collections::hash_map::DefaultHasher, fmt, hash::Hasher, slice::Iter, slice::IterMut, sync::Arc};
 
+use crate::dyn_agg::{self, Scalar};
+use crate::dyn_types::{self, Element};
+use crate::groupby::Agg;
+
+/// A column of some element type. The row operations are object safe, so a
+/// frame can work on its columns without knowing their types.
+#[allow(clippy::len_without_is_empty)]
 pub trait Column {
     fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
     fn len(&self) -> usize;
+
+    /// the registered name of the element type, or its Rust name
+    fn type_name(&self) -> &'static str;
+    /// the value at row `i` as shown in a table
+    fn fmt_value(&self, i: usize) -> String;
+    /// whether row `i` equals row `j` of `other`, false if `other` holds
+    /// another type
+    fn eq_value(&self, i: usize, other: &dyn Column, j: usize) -> bool;
+    fn hash_value(&self, i: usize, state: &mut dyn Hasher);
+    /// order of rows `i` and `j`, None if the type has no order
+    fn cmp_rows(&self, i: usize, j: usize) -> Option<Ordering>;
+    /// new column of the rows at `idx`, in that order, panics if an index is
+    /// out of range
+    fn take(&self, idx: &[usize]) -> Box<dyn Column>;
+    /// new column of the rows where `mask` is true, rows past the end of a
+    /// shorter mask are dropped
+    fn filter(&self, mask: &[bool]) -> Box<dyn Column>;
+    /// new column of rows `offset..offset + len`, clamped to the column. This
+    /// copies, `ColumnView` shares the rows instead.
+    fn slice(&self, offset: usize, len: usize) -> Box<dyn Column>;
+    fn clone_box(&self) -> Box<dyn Column>;
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Col<T: 'static>(Vec<T>);
 
+#[allow(clippy::new_without_default)]
 impl<T> Col<T> {
     pub fn new() -> Col<T> {
         Col(Vec::<T>::new())
@@ -19,9 +52,23 @@ impl<T> Col<T> {
     pub fn iter(&self) -> Iter<'_, T> {
         self.0.iter()
     }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.0
+    }
+
+    // mutable access is by slice only, so a column in a frame cannot change
+    // length
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.0.iter_mut()
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.0
+    }
 }
 
-impl<T> Column for Col<T> {
+impl<T: Element> Column for Col<T> {
     fn len(&self) -> usize {
         self.0.len()
     }
@@ -29,14 +76,57 @@ impl<T> Column for Col<T> {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        dyn_types::name_of::<T>().unwrap_or(std::any::type_name::<T>())
+    }
+
+    fn fmt_value(&self, i: usize) -> String {
+        self.0[i].fmt_value()
+    }
+
+    fn eq_value(&self, i: usize, other: &dyn Column, j: usize) -> bool {
+        other.as_any().downcast_ref::<Col<T>>().is_some_and(|o| self.0[i].eq_value(&o.0[j]))
+    }
+
+    fn hash_value(&self, i: usize, state: &mut dyn Hasher) {
+        self.0[i].hash_value(state)
+    }
+
+    fn cmp_rows(&self, i: usize, j: usize) -> Option<Ordering> {
+        self.0[i].cmp_value(&self.0[j])
+    }
+
+    fn take(&self, idx: &[usize]) -> Box<dyn Column> {
+        Box::new(Col(idx.iter().map(|i| self.0[*i].clone()).collect()))
+    }
+
+    fn filter(&self, mask: &[bool]) -> Box<dyn Column> {
+        Box::new(Col(self.0.iter().zip(mask).filter(|(_, m)| **m).map(|(x, _)| x.clone()).collect()))
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Box<dyn Column> {
+        let start = offset.min(self.0.len());
+        let end = start.saturating_add(len).min(self.0.len());
+        Box::new(Col(self.0[start..end].to_vec()))
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
 }
 
 pub trait AsVec {
     fn as_vec<T: 'static>(&self) -> &Vec<T>;
     fn as_col<T>(&self) -> &Col<T>;
+    fn as_col_mut<T>(&mut self) -> &mut Col<T>;
 }
 
-impl AsVec for dyn Column {
+impl AsVec for dyn Column + '_ {
     fn as_vec<T: 'static>(&self) -> &Vec<T> {
         &self.as_any().downcast_ref::<Col<T>>().expect("cannot convert to vec of type").0
     }
@@ -44,20 +134,31 @@ impl AsVec for dyn Column {
     fn as_col<T>(&self) -> &Col<T> {
         self.as_any().downcast_ref::<Col<T>>().expect("cannot convert to col of specified type")
     }
+
+    fn as_col_mut<T>(&mut self) -> &mut Col<T> {
+        self.as_any_mut().downcast_mut::<Col<T>>().expect("cannot convert to col of specified type")
+    }
 }
 
-type ColRef = Arc<dyn Column>;
+pub type ColRef = Arc<dyn Column>;
 type DFCols = HashMap<String, ColRef>;
+/// Cloning a frame is cheap, the clone shares its columns until one side
+/// modifies them through `get_col_mut`
+#[derive(Clone)]
 pub struct DataFrame {
     len: usize,
     columns: DFCols,
+    // column names in the order they were added
+    names: Vec<String>,
 }
 
+#[allow(clippy::new_without_default, clippy::len_without_is_empty)]
 impl DataFrame {
     pub fn new() -> DataFrame {
         DataFrame {
             len: 0,
             columns: HashMap::new(),
+            names: Vec::new(),
         }
     }
 
@@ -65,21 +166,312 @@ impl DataFrame {
         self.len
     }
 
-    pub fn add_col<T>(&mut self, n: String, c: Col<T>) {
+    pub fn add_col<T: Element>(&mut self, n: String, c: Col<T>) {
+        self.add_ref(n, Arc::new(c));
+    }
+
+    pub(crate) fn add_ref(&mut self, n: String, c: ColRef) {
         if self.columns.is_empty() {
             self.len = c.len();
         } else {
+            #[allow(clippy::collapsible_else_if)]
             if c.len() != self.len() {
                 panic!("column lengths don't match!");
             }
         }
-        self.columns.insert(n, Arc::new(c));
+        if !self.columns.contains_key(&n) {
+            self.names.push(n.clone());
+        }
+        self.columns.insert(n, c);
     }
 
     pub fn get_col(&self, n: &str) -> ColRef {
         self.columns.get(n).unwrap().clone()
     }
 
+    /// mutable access to column `n`. A column shared with another frame or a
+    /// `get_col` handle is copied first (copy on write).
+    pub fn get_col_mut(&mut self, n: &str) -> Result<&mut dyn Column, String> {
+        let c = self.columns.get_mut(n).ok_or_else(|| format!("no column named '{}'", n))?;
+        if Arc::get_mut(c).is_none() {
+            *c = Arc::from(c.clone_box());
+        }
+        Ok(Arc::get_mut(c).expect("column is not shared after copying"))
+    }
+
+    /// column names in the order they were added
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// iterate over (name, column) pairs in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ColRef)> {
+        self.names.iter().map(|n| (n.as_str(), &self.columns[n]))
+    }
+
+    /// view of every row, sharing the columns
+    pub fn view(&self) -> DataFrameView {
+        DataFrameView { df: self.clone(), offset: 0, len: self.len }
+    }
+
+    /// view of `len` rows starting at `offset`, clamped to the frame. Nothing
+    /// is copied, the view shares the frame's columns.
+    pub fn slice(&self, offset: usize, len: usize) -> DataFrameView {
+        self.view().slice(offset, len)
+    }
+
+    /// views of consecutive batches of at most `size` rows
+    pub fn batches(&self, size: usize) -> Batches {
+        assert!(size > 0, "batch size must be non-zero");
+        Batches { view: self.view(), size }
+    }
+
+    fn map_columns(&self, f: impl Fn(&dyn Column) -> Box<dyn Column>) -> DataFrame {
+        let mut df = DataFrame::new();
+        for (n, c) in self.iter() {
+            df.add_ref(n.to_string(), Arc::from(f(c.as_ref())));
+        }
+        df
+    }
+
+    /// new frame with the rows at `idx`, in that order, failing if an index
+    /// is out of range
+    pub fn take(&self, idx: &[usize]) -> Result<DataFrame, String> {
+        if let Some(i) = idx.iter().find(|i| **i >= self.len) {
+            return Err(format!("row {} is out of range for a frame of {} rows", i, self.len));
+        }
+        Ok(self.map_columns(|c| c.take(idx)))
+    }
+
+    /// new frame with the rows where `mask` is true, failing if the mask is
+    /// not one value per row
+    pub fn filter(&self, mask: &[bool]) -> Result<DataFrame, String> {
+        if mask.len() != self.len {
+            return Err(format!("mask of length {} for a frame of {} rows", mask.len(), self.len));
+        }
+        Ok(self.map_columns(|c| c.filter(mask)))
+    }
+
+    /// new frame with the first `n` rows, copied. `slice` gives a view.
+    pub fn head(&self, n: usize) -> DataFrame {
+        self.map_columns(|c| c.slice(0, n))
+    }
+
+    /// new frame with the rows sorted by the columns `by`, the first column
+    /// deciding first. The sort is stable, and fails if a column's type has
+    /// no order.
+    pub fn sort(&self, by: &[&str], descending: bool) -> Result<DataFrame, String> {
+        let mut keys = Vec::new();
+        for n in by {
+            let c = self.columns.get(*n).ok_or_else(|| format!("no column named '{}'", n))?;
+            if c.len() > 0 && c.cmp_rows(0, 0).is_none() {
+                return Err(format!("column '{}' of type {} cannot be sorted", n, c.type_name()));
+            }
+            keys.push(c.as_ref());
+        }
+        let mut idx: Vec<usize> = (0..self.len).collect();
+        idx.sort_by(|i, j| {
+            let o = keys.iter()
+                .map(|c| c.cmp_rows(*i, *j).unwrap_or(Ordering::Equal))
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal);
+            if descending { o.reverse() } else { o }
+        });
+        self.take(&idx)
+    }
+
+    /// `agg` of the numeric column `n`, see `dyn_agg::aggregate`
+    pub fn agg(&self, n: &str, agg: Agg) -> Result<Scalar, String> {
+        let c = self.columns.get(n).ok_or_else(|| format!("no column named '{}'", n))?;
+        dyn_agg::aggregate(c.as_ref(), agg).map_err(|e| format!("column '{}': {}", n, e))
+    }
+
+    pub fn sum(&self, n: &str) -> Result<Scalar, String> {
+        self.agg(n, Agg::Sum)
+    }
+
+    pub fn mean(&self, n: &str) -> Result<Scalar, String> {
+        self.agg(n, Agg::Mean)
+    }
+
+    pub fn min(&self, n: &str) -> Result<Scalar, String> {
+        self.agg(n, Agg::Min)
+    }
+
+    pub fn max(&self, n: &str) -> Result<Scalar, String> {
+        self.agg(n, Agg::Max)
+    }
+
+    /// number of values of `n` that are not NaN
+    pub fn count(&self, n: &str) -> Result<Scalar, String> {
+        self.agg(n, Agg::Count)
+    }
+
+    /// new frame keeping the first of each set of equal rows
+    pub fn unique(&self) -> DataFrame {
+        let cols: Vec<&dyn Column> = self.iter().map(|(_, c)| c.as_ref()).collect();
+        let row_eq = |i: usize, j: usize| cols.iter().all(|c| c.eq_value(i, *c, j));
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut keep = Vec::new();
+        for i in 0..self.len {
+            let mut h = DefaultHasher::new();
+            cols.iter().for_each(|c| c.hash_value(i, &mut h));
+            let rows = seen.entry(h.finish()).or_default();
+            if !rows.iter().any(|j| row_eq(i, *j)) {
+                rows.push(i);
+                keep.push(i);
+            }
+        }
+        self.map_columns(|c| c.take(&keep))
+    }
+}
+
+/// Window of rows over a shared column. It holds the column's `Arc`, so it
+/// can outlive the frame it came from, and nothing is copied until
+/// `to_column`.
+#[derive(Clone)]
+pub struct ColumnView {
+    col: ColRef,
+    offset: usize,
+    len: usize,
+}
+
+impl ColumnView {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the viewed values, None if the column does not hold `T`
+    pub fn as_slice<T: 'static>(&self) -> Option<&[T]> {
+        let c = self.col.as_any().downcast_ref::<Col<T>>()?;
+        Some(&c.0[self.offset..self.offset + self.len])
+    }
+
+    /// view of `len` rows starting at `offset` within this view
+    pub fn slice(&self, offset: usize, len: usize) -> ColumnView {
+        let start = offset.min(self.len);
+        let end = start.saturating_add(len).min(self.len);
+        ColumnView { col: Arc::clone(&self.col), offset: self.offset + start, len: end - start }
+    }
+
+    pub fn fmt_value(&self, i: usize) -> String {
+        assert!(i < self.len, "row {} is out of range for a view of {} rows", i, self.len);
+        self.col.fmt_value(self.offset + i)
+    }
+
+    /// copy the viewed rows out into a column of their own
+    pub fn to_column(&self) -> ColRef {
+        Arc::from(self.col.slice(self.offset, self.len))
+    }
+}
+
+/// Window of rows over a frame, created by `DataFrame::slice`. Columns are
+/// handed out as `ColumnView`s sharing the frame's columns.
+#[derive(Clone)]
+pub struct DataFrameView {
+    df: DataFrame,
+    offset: usize,
+    len: usize,
+}
+
+impl DataFrameView {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.df.names()
+    }
+
+    pub fn get(&self, n: &str) -> Option<ColumnView> {
+        let col = Arc::clone(self.df.columns.get(n)?);
+        Some(ColumnView { col, offset: self.offset, len: self.len })
+    }
+
+    /// view of `len` rows starting at `offset` within this view
+    pub fn slice(&self, offset: usize, len: usize) -> DataFrameView {
+        let start = offset.min(self.len);
+        let end = start.saturating_add(len).min(self.len);
+        DataFrameView { df: self.df.clone(), offset: self.offset + start, len: end - start }
+    }
+
+    pub fn head(&self, n: usize) -> DataFrameView {
+        self.slice(0, n)
+    }
+
+    pub fn tail(&self, n: usize) -> DataFrameView {
+        self.slice(self.len.saturating_sub(n), n)
+    }
+
+    /// copy the viewed rows out into a frame of their own
+    pub fn to_frame(&self) -> DataFrame {
+        self.df.map_columns(|c| c.slice(self.offset, self.len))
+    }
+}
+
+/// Iterator returned by `DataFrame::batches`
+pub struct Batches {
+    view: DataFrameView,
+    size: usize,
+}
+
+impl Iterator for Batches {
+    type Item = DataFrameView;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.view.is_empty() {
+            return None;
+        }
+        let batch = self.view.head(self.size);
+        self.view = self.view.slice(batch.len(), self.view.len());
+        Some(batch)
+    }
+}
+
+/// Frames are equal when they have the same columns in the same order, with
+/// equal values by their `Element::eq_value`
+impl PartialEq for DataFrame {
+    fn eq(&self, other: &DataFrame) -> bool {
+        self.len == other.len
+            && self.names == other.names
+            && self.iter().zip(other.iter())
+                .all(|((_, a), (_, b))| (0..self.len).all(|i| a.eq_value(i, b.as_ref(), i)))
+    }
+}
+
+/// aligned table of every row
+impl fmt::Display for DataFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<Vec<String>> = self.iter()
+            .map(|(n, c)| {
+                let mut v = vec![n.to_string()];
+                v.extend((0..self.len).map(|i| c.fmt_value(i)));
+                v
+            })
+            .collect();
+        let widths: Vec<usize> = cells.iter().map(|v| v.iter().map(|s| s.chars().count()).max().unwrap_or(0)).collect();
+        let n_lines = cells.first().map_or(0, Vec::len);
+        for line in 0..n_lines {
+            let row: Vec<String> = cells.iter().zip(&widths).map(|(v, w)| format!("{:>w$}", v[line], w = w)).collect();
+            write!(f, " {} ", row.join(" | "))?;
+            if line == 0 {
+                let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+                write!(f, "\n-{}-", rule.join("-+-"))?;
+            }
+            if line + 1 < n_lines {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 
@@ -128,5 +520,75 @@ mod test {
 
         let csum: i32 = df.get_col("col1").as_col::<i32>().iter().sum();
         assert_eq!(csum, 10);
+        assert_eq!(df.sum("col1"), Ok(Scalar::I64(10)));
+    }
+
+    #[test]
+    fn test_get_col_mut() {
+        let mut df = DataFrame::new();
+        df.add_col("a".to_string(), Col(vec![1, 2, 3]));
+        let first = Arc::as_ptr(&df.get_col("a")) as *const u8;
+        df.get_col_mut("a").unwrap().as_col_mut::<i32>().as_mut_slice()[0] = 10;
+        assert_eq!(Arc::as_ptr(&df.get_col("a")) as *const u8, first);
+
+        let copy = df.clone();
+        let handle = df.get_col("a");
+        for x in df.get_col_mut("a").unwrap().as_col_mut::<i32>().iter_mut() {
+            *x *= 2;
+        }
+        assert_eq!(df.get_col("a").as_vec::<i32>(), &vec![20, 4, 6]);
+        assert_eq!(copy.get_col("a").as_vec::<i32>(), &vec![10, 2, 3]);
+        assert_eq!(handle.as_vec::<i32>(), &vec![10, 2, 3]);
+        assert!(df.get_col_mut("missing").is_err());
+    }
+
+    #[test]
+    fn test_row_ops() {
+        let mut df = DataFrame::new();
+        df.add_col("k".to_string(), Col(vec!["b".to_string(), "a".to_string(), "b".to_string(), "a".to_string()]));
+        df.add_col("v".to_string(), Col(vec![1.0, f64::NAN, 0.5, 2.0]));
+        assert_eq!(df.get_col("v").type_name(), "f64");
+        assert_eq!(df.get_col("v").fmt_value(1), "NaN");
+
+        let s = df.sort(&["k", "v"], false).unwrap();
+        let v = s.get_col("v");
+        assert!(v.as_vec::<f64>()[1].is_nan());
+        assert_eq!([v.as_vec::<f64>()[0], v.as_vec::<f64>()[2], v.as_vec::<f64>()[3]], [2.0, 0.5, 1.0]);
+        let s = df.sort(&["v"], true).unwrap();
+        assert_eq!(s.get_col("k").as_vec::<String>(), &vec!["a", "a", "b", "b"]);
+        assert!(df.sort(&["missing"], false).is_err());
+
+        assert!(df.head(2) == df.take(&[0, 1]).unwrap());
+        assert_eq!(df.head(10).len(), 4);
+        assert!(df.take(&[0, 4]).is_err());
+        assert!(df.filter(&[true, false]).is_err());
+        let f = df.filter(&[false, true, false, true]).unwrap();
+        assert_eq!(f.get_col("k").as_vec::<String>(), &vec!["a", "a"]);
+        assert_eq!(df.get_col("v").slice(3, 5).len(), 1);
+        assert_eq!(df.get_col("v").slice(9, 1).len(), 0);
+    }
+
+    #[test]
+    fn test_views() {
+        let mut df = DataFrame::new();
+        df.add_col("a".to_string(), Col((0..10).collect::<Vec<i32>>()));
+        df.add_col("b".to_string(), Col((0..10).map(|i| i.to_string()).collect::<Vec<_>>()));
+        let v = df.slice(2, 5);
+        assert_eq!(v.len(), 5);
+        assert_eq!(v.names().collect::<Vec<_>>(), vec!["a", "b"]);
+        let a = v.get("a").unwrap();
+        assert_eq!(a.as_slice::<i32>(), Some(&[2, 3, 4, 5, 6][..]));
+        assert!(a.as_slice::<f64>().is_none());
+        // the view points into the frame's buffer
+        assert!(std::ptr::eq(&a.as_slice::<i32>().unwrap()[0], &df.get_col("a").as_vec::<i32>()[2]));
+        assert_eq!(v.tail(2).get("b").unwrap().fmt_value(1), "6");
+        assert_eq!(a.slice(3, 10).len(), 2);
+        assert_eq!(df.slice(8, 5).len(), 2);
+        assert!(df.slice(20, 1).is_empty());
+        assert!(v.head(2).to_frame() == df.take(&[2, 3]).unwrap());
+        assert_eq!(a.to_column().as_vec::<i32>(), &vec![2, 3, 4, 5, 6]);
+
+        let lens: Vec<usize> = df.batches(4).map(|b| b.len()).collect();
+        assert_eq!(lens, vec![4, 4, 2]);
     }
 }
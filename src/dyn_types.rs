@@ -0,0 +1,291 @@
+//! Element types for `dyn_df` columns.
+//!
+//! A `dyn_df::Col<T>` holds any `T: Element`, and the `Element` methods are
+//! what lets a frame display, compare, hash and sort values behind a
+//! `dyn Column`. Registering `T` gives it a name (and with the `serde`
+//! feature, serialization) so frames holding `Col<T>` columns can be written
+//! and read back. The common primitives and `String` are registered from the
+//! start.
+
+use std::any::TypeId;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{OnceLock, RwLock};
+#[cfg(feature = "serde")]
+use std::sync::Arc;
+
+use crate::dyn_df::{Col, Column};
+#[cfg(feature = "serde")]
+use crate::dyn_df::{AsVec, ColRef};
+
+/// Behaviour a type supplies to be stored in a `dyn_df` column
+pub trait Element: Clone + Send + Sync + 'static {
+    /// the value as shown in a table
+    fn fmt_value(&self) -> String;
+    fn eq_value(&self, other: &Self) -> bool;
+    /// must agree with `eq_value`, equal values hash the same
+    fn hash_value(&self, state: &mut dyn Hasher);
+
+    /// order of two values for sorting, None for types without one
+    fn cmp_value(&self, _other: &Self) -> Option<Ordering> {
+        None
+    }
+}
+
+macro_rules! element {
+    ($($t:ty),*) => {
+        $(impl Element for $t {
+            fn fmt_value(&self) -> String {
+                self.to_string()
+            }
+
+            fn eq_value(&self, other: &Self) -> bool {
+                self == other
+            }
+
+            fn hash_value(&self, mut state: &mut dyn Hasher) {
+                self.hash(&mut state)
+            }
+
+            fn cmp_value(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        })*
+    };
+}
+
+// floats compare and hash by their bits, so NaN equals NaN, and sort by
+// `total_cmp`, which puts NaN last
+macro_rules! float_element {
+    ($($t:ty),*) => {
+        $(impl Element for $t {
+            fn fmt_value(&self) -> String {
+                format!("{:?}", self)
+            }
+
+            fn eq_value(&self, other: &Self) -> bool {
+                self.to_bits() == other.to_bits()
+            }
+
+            fn hash_value(&self, mut state: &mut dyn Hasher) {
+                self.to_bits().hash(&mut state)
+            }
+
+            fn cmp_value(&self, other: &Self) -> Option<Ordering> {
+                Some(self.total_cmp(other))
+            }
+        })*
+    };
+}
+
+element!(i32, i64, u32, u64, bool, String);
+float_element!(f32, f64);
+
+#[cfg(feature = "serde")]
+type ToJson = fn(&dyn Column) -> Result<serde_json::Value, String>;
+#[cfg(feature = "serde")]
+type FromJson = fn(serde_json::Value) -> Result<ColRef, String>;
+
+/// The registered name of a type, and how to serialize its columns
+#[derive(Clone, Copy)]
+pub struct TypeInfo {
+    pub name: &'static str,
+    // registered from the start, these cannot be registered again
+    builtin: bool,
+    #[cfg(feature = "serde")]
+    serde: Option<(ToJson, FromJson)>,
+}
+
+#[cfg(feature = "serde")]
+fn to_json<T: Element + serde::Serialize>(c: &dyn Column) -> Result<serde_json::Value, String> {
+    serde_json::to_value(c.as_col::<T>()).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "serde")]
+fn from_json<T: Element + serde::de::DeserializeOwned>(v: serde_json::Value) -> Result<ColRef, String> {
+    let c: Col<T> = serde_json::from_value(v).map_err(|e| e.to_string())?;
+    Ok(Arc::new(c))
+}
+
+impl TypeInfo {
+    fn of(name: &'static str) -> TypeInfo {
+        TypeInfo {
+            name,
+            builtin: false,
+            #[cfg(feature = "serde")]
+            serde: None,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn with_serde<T: Element + serde::Serialize + serde::de::DeserializeOwned>(name: &'static str) -> TypeInfo {
+        TypeInfo { serde: Some((to_json::<T>, from_json::<T>)), ..TypeInfo::of(name) }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self, c: &dyn Column) -> Result<serde_json::Value, String> {
+        let (to, _) = self.serde.ok_or_else(|| format!("type '{}' is not serializable", self.name))?;
+        to(c)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(&self, v: serde_json::Value) -> Result<ColRef, String> {
+        let (_, from) = self.serde.ok_or_else(|| format!("type '{}' is not serializable", self.name))?;
+        from(v)
+    }
+}
+
+type Registry = RwLock<HashMap<TypeId, TypeInfo>>;
+
+macro_rules! builtins {
+    ($m:ident, $(($name:literal, $t:ty)),*) => {
+        $(
+            #[cfg(feature = "serde")]
+            $m.insert(TypeId::of::<Col<$t>>(), TypeInfo { builtin: true, ..TypeInfo::with_serde::<$t>($name) });
+            #[cfg(not(feature = "serde"))]
+            $m.insert(TypeId::of::<Col<$t>>(), TypeInfo { builtin: true, ..TypeInfo::of($name) });
+        )*
+    };
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut m = HashMap::new();
+        builtins!(m, ("i32", i32), ("i64", i64), ("u32", u32), ("u64", u64), ("f32", f32), ("f64", f64),
+            ("bool", bool), ("String", String));
+        RwLock::new(m)
+    })
+}
+
+fn insert(id: TypeId, info: TypeInfo) -> Result<(), String> {
+    let mut r = registry().write().unwrap();
+    if r.iter().any(|(t, i)| i.name == info.name && *t != id) {
+        return Err(format!("type name '{}' is already registered", info.name));
+    }
+    if let Some(old) = r.get(&id).filter(|i| i.builtin) {
+        return Err(format!("builtin type '{}' cannot be registered again", old.name));
+    }
+    #[cfg(feature = "serde")]
+    let info = match r.get(&id) {
+        Some(old) if info.serde.is_none() => TypeInfo { serde: old.serde, ..info },
+        _ => info,
+    };
+    r.insert(id, info);
+    Ok(())
+}
+
+/// register `T` under `name`, the name `Col<T>` columns report as their
+/// `type_name`. Registering a type again replaces its name, keeping its
+/// serialization if the new registration has none. A name can only be used
+/// by one type, and the builtin types cannot be registered again.
+pub fn register<T: Element>(name: &'static str) -> Result<(), String> {
+    insert(TypeId::of::<Col<T>>(), TypeInfo::of(name))
+}
+
+/// `register`, also allowing frames holding `Col<T>` columns to serialize
+#[cfg(feature = "serde")]
+pub fn register_serde<T>(name: &'static str) -> Result<(), String>
+where
+    T: Element + serde::Serialize + serde::de::DeserializeOwned,
+{
+    insert(TypeId::of::<Col<T>>(), TypeInfo::with_serde::<T>(name))
+}
+
+/// the registration of the type of `c`
+pub fn lookup(c: &dyn Column) -> Option<TypeInfo> {
+    registry().read().unwrap().get(&c.as_any().type_id()).copied()
+}
+
+/// the name `T` is registered under
+pub(crate) fn name_of<T: Element>() -> Option<&'static str> {
+    registry().read().unwrap().get(&TypeId::of::<Col<T>>()).map(|i| i.name)
+}
+
+/// the registration of the type named `name`
+pub fn lookup_name(name: &str) -> Option<TypeInfo> {
+    registry().read().unwrap().values().find(|i| i.name == name).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dyn_df::DataFrame;
+
+    /// fixed point number, equal when the values are equal whatever the scale
+    #[derive(Clone, Debug)]
+    pub(crate) struct Decimal {
+        pub units: i64,
+        pub scale: u32,
+    }
+
+    impl Decimal {
+        fn normal(&self) -> (i64, u32) {
+            let (mut u, mut s) = (self.units, self.scale);
+            while s > 0 && u % 10 == 0 {
+                u /= 10;
+                s -= 1;
+            }
+            (u, s)
+        }
+    }
+
+    impl Element for Decimal {
+        fn fmt_value(&self) -> String {
+            let (p, u) = (10i64.pow(self.scale), self.units.abs());
+            let sign = if self.units < 0 { "-" } else { "" };
+            format!("{}{}.{:0w$}", sign, u / p, u % p, w = self.scale as usize)
+        }
+
+        fn eq_value(&self, other: &Self) -> bool {
+            self.normal() == other.normal()
+        }
+
+        fn hash_value(&self, mut state: &mut dyn Hasher) {
+            self.normal().hash(&mut state)
+        }
+    }
+
+    fn dec(v: &[(i64, u32)]) -> Col<Decimal> {
+        Col::from_vec(v.iter().map(|(units, scale)| Decimal { units: *units, scale: *scale }).collect())
+    }
+
+    #[test]
+    fn test_register() {
+        register::<Decimal>("decimal").unwrap();
+        register::<Decimal>("decimal").unwrap();
+        assert!(register::<u64>("decimal").is_err());
+        assert!(register::<u64>("i32").is_err());
+        assert!(register::<i32>("int").is_err());
+        assert_eq!(Col::from_vec(vec![1]).type_name(), "i32");
+
+        let c = dec(&[(1250, 2), (125, 1), (-5, 1)]);
+        let info = lookup(&c).unwrap();
+        assert_eq!(info.name, "decimal");
+        assert_eq!(c.fmt_value(0), "12.50");
+        assert!(c.eq_value(0, &c, 1));
+        assert!(!c.eq_value(0, &Col::from_vec(vec![1]), 0));
+        assert_eq!(c.take(&[2, 0]).len(), 2);
+        assert_eq!(c.type_name(), "decimal");
+        assert_eq!(Col::from_vec(vec![1.5f32]).type_name(), "f32");
+        assert!(lookup_name("f64").is_some());
+        assert!(lookup_name("nope").is_none());
+    }
+
+    #[test]
+    fn test_frame_ops() {
+        let mut df = DataFrame::new();
+        df.add_col("id".to_string(), Col::from_vec(vec![1, 2, 2, 3]));
+        df.add_col("px".to_string(), dec(&[(100, 2), (15, 1), (150, 2), (-5, 1)]));
+        assert_eq!(df.to_string(), " id |   px \n----+------\n  1 | 1.00 \n  2 |  1.5 \n  2 | 1.50 \n  3 | -0.5 ");
+
+        let u = df.unique();
+        assert_eq!(u.len(), 3);
+        assert!(df.take(&[0, 1, 3]).unwrap() == u);
+        assert!(df.filter(&[true, true, false, true]).unwrap() == u);
+        assert!(df != u);
+        assert!(df.sort(&["px"], false).is_err());
+        assert!(df.sort(&["id"], true).unwrap() == df.take(&[3, 1, 2, 0]).unwrap());
+    }
+}
@@ -0,0 +1,193 @@
+//! Bucketing and encoding `enum_df` columns for model features.
+//!
+//! Bins are closed on the left, `[e_i, e_i+1)`, except the last which also
+//! holds its right edge. `cut` and `qcut` give each row its bin number as a
+//! `Column::I`, with -1 for values outside the edges and for NaN. The edges
+//! `qcut` and `histogram` compute come from the finite values only, so
+//! infinities fall outside every bin.
+
+use crate::enum_df::{Column, DataFrame};
+
+/// Counts of values in equal-width bins, `edges` has one more entry than
+/// `counts`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub edges: Vec<f64>,
+    pub counts: Vec<usize>,
+}
+
+/// the values of a numeric column as f64
+pub(crate) fn values(c: &Column) -> Result<Vec<f64>, String> {
+    match c {
+        Column::F(x) => Ok(x.iter().map(|v| *v as f64).collect()),
+        Column::D(x) => Ok(x.clone()),
+        Column::I(x) => Ok(x.iter().map(|v| *v as f64).collect()),
+        _ => Err(format!("expected a numeric column, not {}", c.type_name())),
+    }
+}
+
+fn bin(edges: &[f64], v: f64) -> i32 {
+    let last = edges.len() - 1;
+    if v.is_nan() || v < edges[0] || v > edges[last] {
+        return -1;
+    }
+    match edges.partition_point(|e| *e <= v) {
+        0 => -1,
+        p => (p - 1).min(last - 1) as i32,
+    }
+}
+
+fn check_edges(edges: &[f64]) -> Result<(), String> {
+    if edges.len() < 2 {
+        return Err("need at least two bin edges".to_string());
+    }
+    if !edges.windows(2).all(|w| w[0] < w[1]) {
+        return Err("bin edges must be increasing".to_string());
+    }
+    Ok(())
+}
+
+/// bin number of each value of `c` for the given `edges`
+pub fn cut(c: &Column, edges: &[f64]) -> Result<Column, String> {
+    check_edges(edges)?;
+    Ok(Column::I(values(c)?.into_iter().map(|v| bin(edges, v)).collect()))
+}
+
+/// `q` quantile of sorted values, interpolating between neighbours
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+/// bin number of each value of `c` for `q` bins holding about the same number
+/// of values, and the edges used. Repeated edges are merged so heavily
+/// repeated values can leave fewer than `q` bins.
+pub fn qcut(c: &Column, q: usize) -> Result<(Column, Vec<f64>), String> {
+    if q == 0 {
+        return Err("need at least one bin".to_string());
+    }
+    let v = values(c)?;
+    let mut sorted: Vec<f64> = v.iter().copied().filter(|x| x.is_finite()).collect();
+    if sorted.is_empty() {
+        return Err("cannot bin a column without finite values".to_string());
+    }
+    sorted.sort_by(f64::total_cmp);
+    let mut edges: Vec<f64> = (0..=q).map(|k| quantile(&sorted, k as f64 / q as f64)).collect();
+    edges.dedup();
+    if edges.len() < 2 {
+        return Err("all values are equal".to_string());
+    }
+    Ok((Column::I(v.into_iter().map(|x| bin(&edges, x)).collect()), edges))
+}
+
+/// counts of the values of `c` in `bins` equal-width bins spanning its range
+pub fn histogram(c: &Column, bins: usize) -> Result<Histogram, String> {
+    if bins == 0 {
+        return Err("need at least one bin".to_string());
+    }
+    let v = values(c)?;
+    let (lo, hi) = v.iter().filter(|x| x.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(*x), hi.max(*x)));
+    if lo > hi {
+        return Err("cannot bin a column without finite values".to_string());
+    }
+    // a constant column still gets bins of some width
+    let (lo, hi) = if lo == hi { (lo - 0.5, hi + 0.5) } else { (lo, hi) };
+    let mut edges: Vec<f64> = (0..=bins).map(|k| lo + (hi - lo) * k as f64 / bins as f64).collect();
+    edges[bins] = hi;
+    let mut counts = vec![0; bins];
+    for b in v.into_iter().map(|x| bin(&edges, x)).filter(|b| *b >= 0) {
+        counts[b as usize] += 1;
+    }
+    Ok(Histogram { edges, counts })
+}
+
+/// replace each string column of `cols` by one 0/1 `<col>_<value>` column per
+/// distinct value, in sorted order
+pub fn get_dummies(df: &DataFrame, cols: &[&str]) -> Result<DataFrame, String> {
+    for n in cols {
+        match df.get(n) {
+            None => return Err(format!("no column named '{}'", n)),
+            Some(Column::S(_)) => (),
+            Some(_) => return Err(format!("'{}' is not a string column", n)),
+        }
+    }
+    let mut out = DataFrame::new();
+    for (n, c) in df.iter() {
+        let x = match c {
+            Column::S(x) if cols.contains(&n) => x,
+            _ => {
+                out.addcol(n, c.clone()).expect("columns of a frame have equal length");
+                continue;
+            }
+        };
+        let mut cats: Vec<&String> = x.iter().collect();
+        cats.sort();
+        cats.dedup();
+        for cat in cats {
+            let name = format!("{}_{}", n, cat);
+            if df.get(&name).is_some() || out.get(&name).is_some() {
+                return Err(format!("encoding '{}' would replace column '{}'", n, name));
+            }
+            let ind = x.iter().map(|v| (v == cat) as i32).collect();
+            out.addcol(&name, Column::I(ind)).expect("indicators have the frame's length");
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+
+    fn ints(c: Column) -> Vec<i32> {
+        let Column::I(x) = c else { panic!("expected I column") };
+        x
+    }
+
+    #[test]
+    fn test_cut() {
+        let c = Column::D(vec![-1.0, 0.0, 0.5, 1.0, 2.5, 3.0, 3.5, f64::NAN]);
+        assert_eq!(ints(cut(&c, &[0.0, 1.0, 2.0, 3.0]).unwrap()), vec![-1, 0, 0, 1, 2, 2, -1, -1]);
+        assert!(cut(&c, &[1.0]).is_err());
+        assert!(cut(&c, &[1.0, 1.0]).is_err());
+        assert!(cut(&Column::S(vec!["a".into()]), &[0.0, 1.0]).is_err());
+
+        let (codes, edges) = qcut(&Column::irange(8), 4).unwrap();
+        assert_eq!(edges, vec![0.0, 1.75, 3.5, 5.25, 7.0]);
+        assert_eq!(ints(codes), vec![0, 0, 1, 1, 2, 2, 3, 3]);
+        let (_, edges) = qcut(&Column::I(vec![1, 1, 1, 1, 2]), 4).unwrap();
+        assert_eq!(edges, vec![1.0, 2.0]);
+        assert!(qcut(&Column::I(vec![5, 5]), 2).is_err());
+        let (codes, edges) = qcut(&Column::D(vec![1.0, 2.0, 3.0, f64::INFINITY]), 2).unwrap();
+        assert_eq!(edges, vec![1.0, 2.0, 3.0]);
+        assert_eq!(ints(codes), vec![0, 1, 1, -1]);
+        assert!(qcut(&Column::D(vec![f64::INFINITY]), 2).is_err());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let h = histogram(&Column::F(vec![0.0, 1.0, 1.5, 2.0, 4.0, f32::NAN]), 4).unwrap();
+        assert_eq!(h.edges, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(h.counts, vec![1, 2, 1, 1]);
+        let h = histogram(&Column::I(vec![3, 3]), 2).unwrap();
+        assert_eq!(h.counts, vec![0, 2]);
+        assert!(histogram(&Column::D(vec![f64::NAN]), 2).is_err());
+        let h = histogram(&Column::D(vec![f64::NEG_INFINITY, 1.0, 3.0, f64::INFINITY]), 2).unwrap();
+        assert_eq!((h.edges, h.counts), (vec![1.0, 2.0, 3.0], vec![1, 1]));
+    }
+
+    #[test]
+    fn test_get_dummies() {
+        let df = df! { "id" => [1, 2, 3], "c" => ["b", "a", "b"], "d" => ["x", "x", "y"] };
+        let out = df.one_hot("c").unwrap();
+        assert_eq!(out, df! { "id" => [1, 2, 3], "c_a" => [0, 1, 0], "c_b" => [1, 0, 1], "d" => ["x", "x", "y"] });
+        let out = df.get_dummies(&["c", "d"]).unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["id", "c_a", "c_b", "d_x", "d_y"]);
+        assert!(df.one_hot("id").is_err());
+        assert!(df.one_hot("missing").is_err());
+        assert!(df! { "c" => ["a"], "c_a" => [1] }.one_hot("c").is_err());
+    }
+}
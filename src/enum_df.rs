@@ -1,23 +1,87 @@
 // simple evaluation implementation of columns and dataframe
 
 use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
 
+use crate::compare::{self, CompareOptions, FrameDiff};
+use crate::encode::{self, Histogram};
+use crate::fill::{self, Fill};
+use crate::groupby::{self, Agg};
+use crate::nested::{self, ListColumn, StructColumn};
+use crate::rows::{ColumnType, FromRow, IntoRows, Rows};
+use crate::sample::{self, SampleSize};
+use crate::sketch::{HyperLogLog, Sketch, TDigest};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Column {
     F(Vec<f32>),
     D(Vec<f64>),
     I(Vec<i32>),
     S(Vec<String>),
+    /// a list of values per row, see `nested::ListColumn`
+    L(ListColumn),
+    /// a record of named fields per row, see `nested::StructColumn`
+    R(StructColumn),
+}
+
+/// Borrowed window over a column's data. Views share the buffer of the column
+/// they were taken from, so slicing never copies.
+#[derive(Clone, Copy, Debug)]
+pub enum ColumnView<'a> {
+    F(&'a [f32]),
+    D(&'a [f64]),
+    I(&'a [i32]),
+    S(&'a [String]),
+    /// offsets of the viewed rows (one more than the row count) into the
+    /// list's child column
+    L(&'a [usize], &'a Column),
+    /// struct with the offset and length of the viewed rows
+    R(&'a StructColumn, usize, usize),
 }
 
+/// apply `$op` to the data of a flat column, with the nested variants handled
+/// by the extra match arms
 macro_rules! forward_op {
-    ($in:ident, $op:expr) => {
+    ($t:ident, $in:ident, $op:expr, $($nested:pat => $e:expr),*) => {
         match $in {
-            Column::F(x) => $op(x),
-            Column::D(x) => $op(x),
-            Column::I(x) => $op(x),
-            Column::S(x) => $op(x),
+            $t::F(x) => $op(x),
+            $t::D(x) => $op(x),
+            $t::I(x) => $op(x),
+            $t::S(x) => $op(x),
+            $($nested => $e),*
         }
-    }
+    };
+}
+
+/// apply `$op` to the data of a flat column, wrapping the result back up in the
+/// same variant, with the nested variants handled by the extra match arms
+macro_rules! map_op {
+    ($in:ident, $op:expr, $($nested:pat => $e:expr),*) => {
+        match $in {
+            Column::F(x) => Column::F($op(x)),
+            Column::D(x) => Column::D($op(x)),
+            Column::I(x) => Column::I($op(x)),
+            Column::S(x) => Column::S($op(x)),
+            $($nested => $e),*
+        }
+    };
+}
+
+fn take_vec<T: Clone>(x: &[T], idx: &[usize]) -> Vec<T> {
+    idx.iter().map(|i| x[*i].clone()).collect()
+}
+
+fn filter_vec<T: Clone>(x: &[T], mask: &[bool]) -> Vec<T> {
+    x.iter().zip(mask).filter(|(_, m)| **m).map(|(v, _)| v.clone()).collect()
+}
+
+/// clamp a (offset, len) window to a sequence of length `total`, so that out of
+/// range requests give a shorter (possibly empty) window rather than panicking
+fn clamp_range(total: usize, offset: usize, len: usize) -> Range<usize> {
+    let start = offset.min(total);
+    start..start + len.min(total - start)
 }
 
 impl Column {
@@ -41,34 +105,285 @@ impl Column {
         matches!(self, Column::D(_) | Column::F(_) | Column::I(_))
     }
 
+    /// name of the element type held by the column
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Column::F(_) => "f32",
+            Column::D(_) => "f64",
+            Column::I(_) => "i32",
+            Column::S(_) => "String",
+            Column::L(_) => "list",
+            Column::R(_) => "struct",
+        }
+    }
+
+    /// whether two columns hold the same type, comparing the children of
+    /// nested columns
+    pub fn same_type(&self, other: &Column) -> bool {
+        match (self, other) {
+            (Column::L(a), Column::L(b)) => a.values().same_type(b.values()),
+            (Column::R(a), Column::R(b)) => {
+                a.fields().count() == b.fields().count()
+                    && a.fields().zip(b.fields()).all(|((n, c), (m, d))| n == m && c.same_type(d))
+            }
+            (a, b) => a.type_name() == b.type_name(),
+        }
+    }
+
     pub fn len(&self) -> usize {
-        forward_op!(self, Vec::len)
+        forward_op!(Column, self, Vec::len, Column::L(l) => l.len(), Column::R(s) => s.len())
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    pub fn view(&self) -> ColumnView<'_> {
+        match self {
+            Column::F(x) => ColumnView::F(x),
+            Column::D(x) => ColumnView::D(x),
+            Column::I(x) => ColumnView::I(x),
+            Column::S(x) => ColumnView::S(x),
+            Column::L(l) => ColumnView::L(l.offsets(), l.values()),
+            Column::R(s) => ColumnView::R(s, 0, s.len()),
+        }
+    }
+
+    /// a field of a struct column
+    pub fn field(&self, name: &str) -> Option<&Column> {
+        match self {
+            Column::R(s) => s.field(name),
+            _ => None,
+        }
+    }
+
+    /// view of `len` rows starting at `offset`, clamped to the column length
+    pub fn slice(&self, offset: usize, len: usize) -> ColumnView<'_> {
+        self.view().slice(offset, len)
+    }
+
+    /// new column with the rows at `idx`, in that order
+    pub fn take(&self, idx: &[usize]) -> Column {
+        map_op!(self, |x| take_vec(x, idx),
+            Column::L(l) => Column::L(l.take(idx)),
+            Column::R(s) => Column::R(s.take(idx)))
+    }
+
+    /// new column with the rows where `mask` is true
+    pub fn filter(&self, mask: &[bool]) -> Column {
+        assert_eq!(mask.len(), self.len(), "mask length does not match column length");
+        let idx = || -> Vec<usize> { (0..mask.len()).filter(|i| mask[*i]).collect() };
+        map_op!(self, |x| filter_vec(x, mask),
+            Column::L(l) => Column::L(l.take(&idx())),
+            Column::R(s) => Column::R(s.take(&idx())))
+    }
+
+    /// number of missing values, NaNs in float columns
+    pub fn null_count(&self) -> usize {
+        fill::null_count(self)
+    }
+
+    /// new column with missing values filled, see `fill::Fill`
+    pub fn fill(&self, how: Fill) -> Column {
+        fill::fill(self, how)
+    }
+
+    /// bin number of each value for the given edges, see `encode::cut`
+    pub fn cut(&self, edges: &[f64]) -> Result<Column, String> {
+        encode::cut(self, edges)
+    }
+
+    /// bin number of each value for `q` equally filled bins, and their edges
+    pub fn qcut(&self, q: usize) -> Result<(Column, Vec<f64>), String> {
+        encode::qcut(self, q)
+    }
+
+    /// counts of values in `bins` equal-width bins
+    pub fn histogram(&self, bins: usize) -> Result<Histogram, String> {
+        encode::histogram(self, bins)
+    }
+
+    /// distinct count estimated with a `sketch::HyperLogLog`, within about 1%
+    pub fn approx_n_unique(&self) -> usize {
+        let mut h = HyperLogLog::new(14).expect("valid precision");
+        h.update(self.view()).expect("every column can be counted");
+        h.estimate().round() as usize
+    }
+
+    /// `q` quantile estimated with a `sketch::TDigest`, for numeric columns
+    pub fn approx_quantile(&self, q: f64) -> Result<f64, String> {
+        let mut t = TDigest::new(100.0);
+        t.update(self.view())?;
+        Ok(t.quantile(q))
+    }
+}
+
+impl<'a> ColumnView<'a> {
+    pub fn len(&self) -> usize {
+        forward_op!(ColumnView, self, <[_]>::len,
+            ColumnView::L(offsets, _) => offsets.len() - 1,
+            ColumnView::R(_, _, len) => *len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, ColumnView::D(_) | ColumnView::F(_) | ColumnView::I(_))
+    }
+
+    /// view of `len` rows starting at `offset` within this view
+    pub fn slice(&self, offset: usize, len: usize) -> ColumnView<'a> {
+        let r = clamp_range(self.len(), offset, len);
+        match *self {
+            ColumnView::F(x) => ColumnView::F(&x[r]),
+            ColumnView::D(x) => ColumnView::D(&x[r]),
+            ColumnView::I(x) => ColumnView::I(&x[r]),
+            ColumnView::S(x) => ColumnView::S(&x[r]),
+            ColumnView::L(offsets, values) => ColumnView::L(&offsets[r.start..r.end + 1], values),
+            ColumnView::R(s, o, _) => ColumnView::R(s, o + r.start, r.len()),
+        }
+    }
+
+    /// copy the viewed rows out into an owned column
+    pub fn to_column(&self) -> Column {
+        match *self {
+            ColumnView::F(x) => Column::F(x.to_vec()),
+            ColumnView::D(x) => Column::D(x.to_vec()),
+            ColumnView::I(x) => Column::I(x.to_vec()),
+            ColumnView::S(x) => Column::S(x.to_vec()),
+            ColumnView::L(offsets, values) => Column::L(ListColumn::slice(offsets, values)),
+            ColumnView::R(s, o, len) => Column::R(s.slice(o, len)),
+        }
+    }
+
+    /// value at `i` formatted for display, floats always show a decimal point
+    pub(crate) fn fmt_value(&self, i: usize) -> String {
+        match self {
+            ColumnView::F(x) => format!("{:?}", x[i]),
+            ColumnView::D(x) => format!("{:?}", x[i]),
+            ColumnView::I(x) => x[i].to_string(),
+            ColumnView::S(x) => x[i].clone(),
+            ColumnView::L(offsets, values) => {
+                let v = values.view();
+                let items: Vec<String> = (offsets[i]..offsets[i + 1]).map(|j| v.fmt_value(j)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            ColumnView::R(s, o, _) => {
+                let items: Vec<String> = s.fields().map(|(n, c)| format!("{}: {}", n, c.view().fmt_value(o + i))).collect();
+                format!("{{{}}}", items.join(", "))
+            }
+        }
+    }
+}
+
+/// Conversion of plain data into a column, picking the variant from the
+/// element type. Used by the `df!` macro.
+pub trait IntoColumn {
+    fn into_column(self) -> Column;
+}
+
+impl IntoColumn for Column {
+    fn into_column(self) -> Column {
+        self
+    }
+}
+
+impl<T: ColumnType> IntoColumn for Vec<T> {
+    fn into_column(self) -> Column {
+        T::into_column(self)
+    }
+}
+
+impl<T: ColumnType, const N: usize> IntoColumn for [T; N] {
+    fn into_column(self) -> Column {
+        T::into_column(Vec::from(self))
+    }
+}
+
+impl IntoColumn for Vec<&str> {
+    fn into_column(self) -> Column {
+        Column::S(self.into_iter().map(String::from).collect())
+    }
+}
+
+impl<const N: usize> IntoColumn for [&str; N] {
+    fn into_column(self) -> Column {
+        Column::S(self.into_iter().map(String::from).collect())
+    }
+}
+
+impl IntoColumn for ListColumn {
+    fn into_column(self) -> Column {
+        Column::L(self)
+    }
+}
+
+impl IntoColumn for StructColumn {
+    fn into_column(self) -> Column {
+        Column::R(self)
+    }
 }
 
-#[derive(Default)]
+/// Build a `DataFrame` from literal columns. Integer literals give `Column::I`,
+/// float literals `Column::D` (use an `f32` suffix for `Column::F`) and strings
+/// `Column::S`. Panics if the columns differ in length or a name repeats.
+///
+/// ```
+/// use rusttest::df;
+///
+/// let df = df! {
+///     "a" => [1, 2, 3],
+///     "b" => ["x", "y", "z"],
+/// };
+/// assert_eq!(df.len(), 3);
+/// ```
+#[macro_export]
+macro_rules! df {
+    ($($name:expr => $data:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut df = $crate::enum_df::DataFrame::new();
+        $(
+            let name: &str = $name;
+            if df.get(name).is_some() {
+                panic!("df!: column '{}' appears twice", name);
+            }
+            if let Err(e) = df.addcol(name, $crate::enum_df::IntoColumn::into_column($data)) {
+                panic!("df!: column '{}': {}", name, e);
+            }
+        )*
+        df
+    }};
+}
+
+/// Frames are equal when they have the same columns, in the same order, with
+/// exactly equal values. See `compare` for approximate comparison.
+#[derive(Clone, Default, PartialEq)]
 pub struct DataFrame {
     len: usize,
     columns: HashMap<String, Column>,
+    // column names in the order they were added. A CSV batch keeps the
+    // file's column order and a group-by result its keys before the
+    // aggregates, which the map alone would shuffle.
+    names: Vec<String>,
 }
 
 impl DataFrame {
     pub fn new() -> DataFrame {
-        DataFrame{len: 0, columns: HashMap::new()}
+        DataFrame{len: 0, columns: HashMap::new(), names: Vec::new()}
     }
 
     pub fn addcol(&mut self, name: &str, data: Column) -> Result<(), &str> {
-        if self.columns.len() == 0 {
+        if self.columns.is_empty() {
             self.len = data.len();
         }
         else if self.len != data.len() {
             return Err("column length does not match dataframe length");
         }
-        self.columns.insert(name.to_string(), data);
+        if self.columns.insert(name.to_string(), data).is_none() {
+            self.names.push(name.to_string());
+        }
         Ok(())
     }
 
@@ -76,6 +391,10 @@ impl DataFrame {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn get(&self, name: &str) -> Option<&Column> {
         self.columns.get(name)
     }
@@ -83,6 +402,286 @@ impl DataFrame {
     pub fn get_mut(&mut self, name: &str) -> Option<&mut Column> {
         self.columns.get_mut(name)
     }
+
+    /// column names in the order they were added
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// iterate over (name, column) pairs in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Column)> {
+        self.names.iter().map(|n| (n.as_str(), &self.columns[n]))
+    }
+
+    /// consume the frame, yielding its (name, column) pairs in order
+    pub fn into_columns(mut self) -> impl Iterator<Item = (String, Column)> {
+        let cols: Vec<_> = self.names.into_iter()
+            .map(|n| { let c = self.columns.remove(&n).unwrap(); (n, c) })
+            .collect();
+        cols.into_iter()
+    }
+
+    /// build a dataframe with one column per field of `T`
+    pub fn from_rows<T: IntoRows>(rows: Vec<T>) -> DataFrame {
+        T::into_frame(rows)
+    }
+
+    /// iterate over rows converted to `T`, each failing if a field's column is
+    /// missing or of the wrong type
+    pub fn rows<T: FromRow>(&self) -> Rows<'_, T> {
+        Rows::new(self)
+    }
+
+    /// new frame with the rows at `idx`, in that order
+    pub fn take(&self, idx: &[usize]) -> DataFrame {
+        let mut df = DataFrame::new();
+        for (n, c) in self.iter() {
+            df.addcol(n, c.take(idx)).expect("taken columns have equal length");
+        }
+        df.len = idx.len();
+        df
+    }
+
+    /// new frame with the rows where `mask` is true
+    pub fn filter(&self, mask: &[bool]) -> DataFrame {
+        let mut df = DataFrame::new();
+        for (n, c) in self.iter() {
+            df.addcol(n, c.filter(mask)).expect("filtered columns have equal length");
+        }
+        df.len = mask.iter().filter(|m| **m).count();
+        df
+    }
+
+    /// new frame with copies of the named columns, in the given order
+    pub fn select(&self, names: &[&str]) -> Result<DataFrame, String> {
+        let mut df = DataFrame::new();
+        for n in names {
+            let c = self.get(n).ok_or_else(|| format!("no column named '{}'", n))?;
+            df.addcol(n, c.clone()).expect("columns of a frame have equal length");
+        }
+        Ok(df)
+    }
+
+    /// group by the `keys` columns computing `aggs`, see `groupby::GroupBy`
+    pub fn group_by(&self, keys: &[&str], aggs: &[(&str, Agg)]) -> Result<DataFrame, String> {
+        groupby::group_by(self, keys, aggs)
+    }
+
+    /// replace the string column `col` with one 0/1 indicator column per value
+    pub fn one_hot(&self, col: &str) -> Result<DataFrame, String> {
+        encode::get_dummies(self, &[col])
+    }
+
+    /// `one_hot` for several columns at once
+    pub fn get_dummies(&self, cols: &[&str]) -> Result<DataFrame, String> {
+        encode::get_dummies(self, cols)
+    }
+
+    /// random rows, with or without replacement, see `sample::sample`
+    pub fn sample(&self, size: SampleSize, replace: bool, seed: Option<u64>) -> Result<DataFrame, String> {
+        sample::sample(self, size, replace, seed)
+    }
+
+    /// all rows in random order
+    pub fn shuffle(&self, seed: Option<u64>) -> DataFrame {
+        sample::shuffle(self, seed)
+    }
+
+    /// stratified sample, `size` rows (or fraction) from each group of `key`
+    pub fn sample_by(&self, key: &str, size: SampleSize, replace: bool, seed: Option<u64>) -> Result<DataFrame, String> {
+        sample::sample_by(self, key, size, replace, seed)
+    }
+
+    /// one row per element of the list column `col`, see `nested::explode`
+    pub fn explode(&self, col: &str) -> Result<DataFrame, String> {
+        nested::explode(self, col)
+    }
+
+    /// collect the non-key columns into lists per group of the `keys` columns
+    pub fn implode(&self, keys: &[&str]) -> Result<DataFrame, String> {
+        nested::implode(self, keys)
+    }
+
+    /// flatten the struct column `col` into one column per field
+    pub fn unnest(&self, col: &str) -> Result<DataFrame, String> {
+        nested::unnest(self, col)
+    }
+
+    /// new frame with missing values in the `cols` columns filled
+    pub fn fill(&self, cols: &[&str], how: Fill) -> Result<DataFrame, String> {
+        fill::fill_frame(self, &[], cols, how)
+    }
+
+    /// like `fill`, but each group of the `keys` columns is filled on its own so
+    /// values never carry over between groups
+    pub fn fill_by(&self, keys: &[&str], cols: &[&str], how: Fill) -> Result<DataFrame, String> {
+        fill::fill_frame(self, keys, cols, how)
+    }
+
+    /// equality with float tolerance and optional row and column order, see
+    /// `compare::frames_equal`
+    pub fn approx_eq(&self, other: &DataFrame, opts: &CompareOptions) -> bool {
+        compare::frames_equal(self, other, opts)
+    }
+
+    /// rows added, removed and changed from `self` to `other`, matching rows by
+    /// the `keys` columns
+    pub fn diff(&self, other: &DataFrame, keys: &[&str]) -> Result<FrameDiff, String> {
+        compare::diff(self, other, keys, &CompareOptions::default())
+    }
+
+    pub fn view(&self) -> DataFrameView<'_> {
+        DataFrameView { df: self, offset: 0, len: self.len }
+    }
+
+    /// zero-copy view of `len` rows starting at `offset`, clamped to the frame
+    pub fn slice(&self, offset: usize, len: usize) -> DataFrameView<'_> {
+        self.view().slice(offset, len)
+    }
+
+    pub fn head(&self, n: usize) -> DataFrameView<'_> {
+        self.slice(0, n)
+    }
+
+    pub fn tail(&self, n: usize) -> DataFrameView<'_> {
+        self.slice(self.len.saturating_sub(n), n)
+    }
+
+    /// iterate over consecutive views of `size` rows, the last possibly shorter
+    pub fn batches(&self, size: usize) -> Batches<'_> {
+        assert!(size > 0, "batch size must be non-zero");
+        Batches { view: self.view(), size }
+    }
+}
+
+/// Window of rows over a borrowed dataframe. Columns are handed out as
+/// `ColumnView`s into the parent frame, nothing is copied until `to_frame`.
+#[derive(Clone, Copy)]
+pub struct DataFrameView<'a> {
+    df: &'a DataFrame,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a> DataFrameView<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, name: &str) -> Option<ColumnView<'a>> {
+        self.df.get(name).map(|c| c.slice(self.offset, self.len))
+    }
+
+    /// view of `len` rows starting at `offset` within this view
+    pub fn slice(&self, offset: usize, len: usize) -> DataFrameView<'a> {
+        let r = clamp_range(self.len, offset, len);
+        DataFrameView { df: self.df, offset: self.offset + r.start, len: r.len() }
+    }
+
+    pub fn head(&self, n: usize) -> DataFrameView<'a> {
+        self.slice(0, n)
+    }
+
+    pub fn tail(&self, n: usize) -> DataFrameView<'a> {
+        self.slice(self.len.saturating_sub(n), n)
+    }
+
+    /// copy the viewed rows out into an owned dataframe
+    pub fn to_frame(&self) -> DataFrame {
+        let columns = self.df.iter()
+            .map(|(n, c)| (n.to_string(), c.slice(self.offset, self.len).to_column()))
+            .collect();
+        DataFrame { len: self.len, columns, names: self.df.names.clone() }
+    }
+}
+
+/// Iterator returned by `DataFrame::batches`
+pub struct Batches<'a> {
+    view: DataFrameView<'a>,
+    size: usize,
+}
+
+impl<'a> Iterator for Batches<'a> {
+    type Item = DataFrameView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.view.is_empty() {
+            return None;
+        }
+        let batch = self.view.head(self.size);
+        self.view = self.view.slice(batch.len(), self.view.len());
+        Some(batch)
+    }
+}
+
+/// rows shown by `Display` for a frame before eliding the middle
+const DISPLAY_ROWS: usize = 20;
+
+/// write the `head` and `tail` rows of a view as an aligned table, with a `...`
+/// row between them if `tail` is not empty
+fn write_table(f: &mut fmt::Formatter<'_>, view: &DataFrameView, head: Range<usize>, tail: Range<usize>) -> fmt::Result {
+    let cols: Vec<_> = view.df.names().map(|n| (n, view.get(n).unwrap())).collect();
+    let mut cells: Vec<Vec<String>> = cols.iter()
+        .map(|(n, c)| {
+            let mut v = vec![n.to_string()];
+            v.extend(head.clone().map(|i| c.fmt_value(i)));
+            if !tail.is_empty() {
+                v.push("...".to_string());
+                v.extend(tail.clone().map(|i| c.fmt_value(i)));
+            }
+            v
+        })
+        .collect();
+    let widths: Vec<usize> = cells.iter().map(|v| v.iter().map(|s| s.chars().count()).max().unwrap_or(0)).collect();
+    for (v, ((_, c), w)) in cells.iter_mut().zip(cols.iter().zip(&widths)) {
+        for s in v.iter_mut() {
+            *s = if c.is_numeric() { format!("{:>w$}", s, w = w) } else { format!("{:<w$}", s, w = w) };
+        }
+    }
+
+    let n_lines = cells.first().map_or(0, Vec::len);
+    for line in 0..n_lines {
+        let row: Vec<&str> = cells.iter().map(|v| v[line].as_str()).collect();
+        write!(f, " {} ", row.join(" | "))?;
+        if line == 0 {
+            let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+            write!(f, "\n-{}-", rule.join("-+-"))?;
+        }
+        if line + 1 < n_lines {
+            writeln!(f)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for DataFrameView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_table(f, self, 0..self.len, 0..0)
+    }
+}
+
+/// aligned table, frames longer than `DISPLAY_ROWS` show only their first and
+/// last rows
+impl fmt::Display for DataFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let half = DISPLAY_ROWS / 2;
+        if self.len <= DISPLAY_ROWS {
+            write_table(f, &self.view(), 0..self.len, 0..0)?;
+        } else {
+            write_table(f, &self.view(), 0..half, self.len - half..self.len)?;
+        }
+        write!(f, "\n[{} rows x {} columns]", self.len, self.names.len())
+    }
+}
+
+impl fmt::Debug for DataFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
 
@@ -118,6 +717,12 @@ mod tests {
         df.addcol("mycol", Column::F(vec![1.0, 2.0, 3.0])).unwrap();
         assert_eq!(df.len(), 3);
         df.addcol("mycol2", Column::S(vec!["A".to_string(), "B".to_string(), "C".to_string()])).unwrap();
+        df.addcol("a", Column::irange(3)).unwrap();
+        // replacing a column keeps its position
+        df.addcol("mycol", Column::irange(3)).unwrap();
+        assert_eq!(df.names().collect::<Vec<_>>(), vec!["mycol", "mycol2", "a"]);
+        let names: Vec<String> = df.into_columns().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["mycol", "mycol2", "a"]);
     }
 
     #[test]
@@ -129,4 +734,126 @@ mod tests {
         assert_eq!(df.len(), 3);
     }
 
+    #[test]
+    fn test_df_macro() {
+        let df = df! {
+            "i" => [1, 2, 3],
+            "d" => [1.0, 2.0, 3.0],
+            "f" => [1.0f32, 2.0, 3.0],
+            "s" => ["x", "y", "z"],
+            "v" => vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            "c" => Column::irange(3),
+        };
+        assert_eq!(df.len(), 3);
+        assert!(matches!(df.get("i"), Some(Column::I(_))));
+        assert!(matches!(df.get("d"), Some(Column::D(_))));
+        assert!(matches!(df.get("f"), Some(Column::F(_))));
+        assert!(matches!(df.get("s"), Some(Column::S(_))));
+        assert!(matches!(df.get("v"), Some(Column::S(_))));
+        assert!(df! {}.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "column 'b'")]
+    fn test_df_macro_bad_column_lens() {
+        df! { "a" => [1, 2, 3], "b" => [1, 2] };
+    }
+
+    #[test]
+    #[should_panic(expected = "column 'a' appears twice")]
+    fn test_df_macro_repeated_name() {
+        df! { "a" => [1, 2], "a" => [3, 4] };
+    }
+
+    #[test]
+    fn test_take_filter_select() {
+        let df = df! { "a" => [1, 2, 3, 4], "b" => ["w", "x", "y", "z"] };
+        let t = df.take(&[3, 0, 0]);
+        assert_eq!(t.len(), 3);
+        let Some(Column::S(b)) = t.get("b") else { panic!("expected S column") };
+        assert_eq!(b, &vec!["z", "w", "w"]);
+
+        let f = df.filter(&[true, false, true, false]);
+        let Some(Column::I(a)) = f.get("a") else { panic!("expected I column") };
+        assert_eq!(a, &vec![1, 3]);
+        // filtering keeps the row count even with no columns
+        assert_eq!(DataFrame::new().filter(&[]).len(), 0);
+
+        let s = df.select(&["b"]).unwrap();
+        assert_eq!(s.names().collect::<Vec<_>>(), vec!["b"]);
+        assert!(df.select(&["c"]).is_err());
+    }
+
+    #[test]
+    fn test_column_slice() {
+        let c = Column::irange(10);
+        let v = c.slice(2, 3);
+        assert_eq!(v.len(), 3);
+        let ColumnView::I(x) = v else { panic!("expected I view") };
+        assert_eq!(x, &[2, 3, 4]);
+        // shares the buffer of the column
+        let Column::I(orig) = &c else { panic!("expected I column") };
+        assert!(std::ptr::eq(&x[0], &orig[2]));
+        // out of range windows are clamped
+        assert_eq!(c.slice(8, 5).len(), 2);
+        assert!(c.slice(20, 5).is_empty());
+    }
+
+    #[test]
+    fn test_dataframe_slice() {
+        let mut df = DataFrame::new();
+        df.addcol("a", Column::irange(10)).unwrap();
+        df.addcol("b", Column::frange(10)).unwrap();
+        let v = df.slice(3, 4);
+        assert_eq!(v.len(), 4);
+        let Some(ColumnView::F(b)) = v.get("b") else { panic!("expected F view") };
+        assert_eq!(b, &[3.0, 4.0, 5.0, 6.0]);
+        let Some(ColumnView::I(a)) = v.slice(1, 2).get("a") else { panic!("expected I view") };
+        assert_eq!(a, &[4, 5]);
+        assert!(v.get("missing").is_none());
+
+        let owned = v.to_frame();
+        assert_eq!(owned.len(), 4);
+        assert_eq!(owned.get("a").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_dataframe_head_tail() {
+        let mut df = DataFrame::new();
+        df.addcol("a", Column::irange(10)).unwrap();
+        let Some(ColumnView::I(h)) = df.head(3).get("a") else { panic!("expected I view") };
+        assert_eq!(h, &[0, 1, 2]);
+        let Some(ColumnView::I(t)) = df.tail(3).get("a") else { panic!("expected I view") };
+        assert_eq!(t, &[7, 8, 9]);
+        assert_eq!(df.tail(20).len(), 10);
+    }
+
+    #[test]
+    fn test_dataframe_batches() {
+        let mut df = DataFrame::new();
+        df.addcol("a", Column::irange(10)).unwrap();
+        let lens: Vec<usize> = df.batches(4).map(|b| b.len()).collect();
+        assert_eq!(lens, vec![4, 4, 2]);
+        let Some(ColumnView::I(last)) = df.batches(4).last().unwrap().get("a") else {
+            panic!("expected I view")
+        };
+        assert_eq!(last, &[8, 9]);
+    }
+
+    #[test]
+    fn test_display() {
+        let df = df! { "name" => ["a", "bcd"], "x" => [1.5, 10.0], "n" => [7, 100] };
+        let expected = concat!(
+            " name |    x |   n \n",
+            "------+------+-----\n",
+            " a    |  1.5 |   7 \n",
+            " bcd  | 10.0 | 100 ",
+        );
+        assert_eq!(df.head(5).to_string(), expected);
+        assert!(df.to_string().ends_with("[2 rows x 3 columns]"));
+
+        let long = df! { "a" => Column::irange(30) }.to_string();
+        assert_eq!(long.lines().count(), 2 + 20 + 1 + 1);
+        assert!(long.contains(" ... ") && long.contains(" 29 "));
+    }
 }
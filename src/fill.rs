@@ -0,0 +1,223 @@
+//! Repairing missing values in `enum_df` columns.
+//!
+//! Columns carry no validity bitmap, a missing value in an `F` or `D` column
+//! is stored as NaN. `I` columns cannot hold missing values and are left as
+//! they are by every fill.
+
+use crate::enum_df::{Column, DataFrame};
+use crate::groupby;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fill {
+    /// carry the last valid value forward
+    Forward,
+    /// carry the next valid value backward
+    Backward,
+    Value(f64),
+    Mean,
+    Median,
+    /// linear interpolation by row position between the surrounding valid
+    /// values, within a group by the rows' positions in the whole frame
+    Linear,
+}
+
+trait Float: Copy {
+    fn is_nan(self) -> bool;
+    fn to_f64(self) -> f64;
+    fn from_f64(x: f64) -> Self;
+}
+
+macro_rules! float {
+    ($t:ty) => {
+        impl Float for $t {
+            fn is_nan(self) -> bool {
+                <$t>::is_nan(self)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(x: f64) -> Self {
+                x as $t
+            }
+        }
+    };
+}
+
+float!(f32);
+float!(f64);
+
+fn carry<'a, T: Float + 'a>(x: impl Iterator<Item = &'a mut T>) {
+    let mut last = None;
+    for v in x {
+        match (v.is_nan(), last) {
+            (true, Some(l)) => *v = l,
+            (false, _) => last = Some(*v),
+            _ => (),
+        }
+    }
+}
+
+fn median(mut x: Vec<f64>) -> f64 {
+    if x.is_empty() {
+        return f64::NAN;
+    }
+    x.sort_by(f64::total_cmp);
+    let m = x.len() / 2;
+    if x.len().is_multiple_of(2) { (x[m - 1] + x[m]) / 2.0 } else { x[m] }
+}
+
+/// interpolate the NaNs of `x`, where value `i` sits at position `at(i)`
+fn interpolate<T: Float>(x: &mut [T], at: impl Fn(usize) -> usize) {
+    let mut prev: Option<usize> = None;
+    for i in 0..x.len() {
+        if x[i].is_nan() {
+            continue;
+        }
+        if let Some(p) = prev.filter(|p| i - p > 1) {
+            let (a, b) = (x[p].to_f64(), x[i].to_f64());
+            let span = (at(i) - at(p)) as f64;
+            for (k, v) in x[p + 1..i].iter_mut().enumerate() {
+                *v = T::from_f64(a + (b - a) * (at(p + 1 + k) - at(p)) as f64 / span);
+            }
+        }
+        prev = Some(i);
+    }
+}
+
+/// fill the NaNs of `x` in place. Gaps with no valid value to fill from stay
+/// NaN, e.g. leading NaNs for `Forward` or an all-NaN slice for `Mean`
+fn fill_slice<T: Float>(x: &mut [T], how: Fill) {
+    let valid = || x.iter().filter(|v| !v.is_nan()).map(|v| v.to_f64());
+    let value = match how {
+        Fill::Forward => return carry(x.iter_mut()),
+        Fill::Backward => return carry(x.iter_mut().rev()),
+        Fill::Linear => return interpolate(x, |i| i),
+        Fill::Value(v) => v,
+        Fill::Mean => {
+            let (n, sum) = valid().fold((0, 0.0), |(n, s), v| (n + 1, s + v));
+            sum / n as f64
+        }
+        Fill::Median => median(valid().collect()),
+    };
+    for v in x.iter_mut().filter(|v| v.is_nan()) {
+        *v = T::from_f64(value);
+    }
+}
+
+/// fill the values at `rows`, given in ascending order
+fn fill_rows<T: Float>(x: &mut [T], rows: &[usize], how: Fill) {
+    let mut vals: Vec<T> = rows.iter().map(|r| x[*r]).collect();
+    match how {
+        Fill::Linear => interpolate(&mut vals, |i| rows[i]),
+        _ => fill_slice(&mut vals, how),
+    }
+    for (r, v) in rows.iter().zip(vals) {
+        x[*r] = v;
+    }
+}
+
+/// fill a column, either whole or separately within each set of `groups` rows
+fn fill_column(c: &Column, how: Fill, groups: Option<&[Vec<usize>]>) -> Column {
+    let mut c = c.clone();
+    match (&mut c, groups) {
+        (Column::F(x), None) => fill_slice(x, how),
+        (Column::D(x), None) => fill_slice(x, how),
+        (Column::F(x), Some(g)) => g.iter().for_each(|rows| fill_rows(x, rows, how)),
+        (Column::D(x), Some(g)) => g.iter().for_each(|rows| fill_rows(x, rows, how)),
+        _ => (),
+    }
+    c
+}
+
+pub(crate) fn fill(c: &Column, how: Fill) -> Column {
+    fill_column(c, how, None)
+}
+
+/// number of missing values in a column
+pub(crate) fn null_count(c: &Column) -> usize {
+    match c {
+        Column::F(x) => x.iter().filter(|v| v.is_nan()).count(),
+        Column::D(x) => x.iter().filter(|v| v.is_nan()).count(),
+        _ => 0,
+    }
+}
+
+/// fill `cols` of `df`, within each group of the `keys` columns if any are given
+pub fn fill_frame(df: &DataFrame, keys: &[&str], cols: &[&str], how: Fill) -> Result<DataFrame, String> {
+    for n in cols {
+        match df.get(n) {
+            None => return Err(format!("no column named '{}'", n)),
+            Some(c) if !c.is_numeric() => return Err(format!("cannot fill non-numeric column '{}'", n)),
+            Some(_) => (),
+        }
+    }
+    let groups = if keys.is_empty() { None } else { Some(groupby::group_rows(df, keys)?) };
+    let mut out = DataFrame::new();
+    for (n, c) in df.iter() {
+        let c = if cols.contains(&n) { fill_column(c, how, groups.as_deref()) } else { c.clone() };
+        out.addcol(n, c).expect("filled columns keep their length");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+
+    const NAN: f64 = f64::NAN;
+
+    fn filled(x: &[f64], how: Fill) -> Vec<f64> {
+        let Column::D(v) = fill(&Column::D(x.to_vec()), how) else { panic!("expected D column") };
+        v
+    }
+
+    // NaN != NaN, so compare with a sentinel
+    fn show(x: Vec<f64>) -> Vec<f64> {
+        x.into_iter().map(|v| if v.is_nan() { -1.0 } else { v }).collect()
+    }
+
+    #[test]
+    fn test_fill_strategies() {
+        let x = [NAN, 1.0, NAN, NAN, 4.0, NAN];
+        assert_eq!(show(filled(&x, Fill::Forward)), vec![-1.0, 1.0, 1.0, 1.0, 4.0, 4.0]);
+        assert_eq!(show(filled(&x, Fill::Backward)), vec![1.0, 1.0, 4.0, 4.0, 4.0, -1.0]);
+        assert_eq!(filled(&x, Fill::Value(0.0)), vec![0.0, 1.0, 0.0, 0.0, 4.0, 0.0]);
+        assert_eq!(filled(&x, Fill::Mean), vec![2.5, 1.0, 2.5, 2.5, 4.0, 2.5]);
+        assert_eq!(filled(&[NAN, 1.0, 5.0, 2.0], Fill::Median), vec![2.0, 1.0, 5.0, 2.0]);
+        assert_eq!(show(filled(&x, Fill::Linear)), vec![-1.0, 1.0, 2.0, 3.0, 4.0, -1.0]);
+        assert_eq!(show(filled(&[NAN, NAN], Fill::Mean)), vec![-1.0, -1.0]);
+
+        let c = fill(&Column::F(vec![1.0, f32::NAN, 3.0]), Fill::Linear);
+        let Column::F(f) = c else { panic!("expected F column") };
+        assert_eq!(f, vec![1.0, 2.0, 3.0]);
+        assert_eq!(null_count(&Column::D(x.to_vec())), 4);
+    }
+
+    #[test]
+    fn test_fill_by_group() {
+        let df = df! {
+            "k" => ["a", "b", "a", "b", "a"],
+            "v" => [1.0, 10.0, NAN, NAN, 3.0],
+            "n" => [1, 2, 3, 4, 5],
+        };
+        let out = fill_frame(&df, &["k"], &["v", "n"], Fill::Forward).unwrap();
+        let Some(Column::D(v)) = out.get("v") else { panic!("expected D column") };
+        assert_eq!(v, &vec![1.0, 10.0, 1.0, 10.0, 3.0]);
+        let out = fill_frame(&df, &["k"], &["v"], Fill::Mean).unwrap();
+        let Some(Column::D(v)) = out.get("v") else { panic!("expected D column") };
+        assert_eq!(v, &vec![1.0, 10.0, 2.0, 10.0, 3.0]);
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["k", "v", "n"]);
+
+        // the gaps between a group's rows count, not just their order
+        let df = df! { "k" => ["a", "b", "a", "b", "b", "a"], "v" => [0.0, 1.0, NAN, 2.0, 3.0, 8.0] };
+        let out = fill_frame(&df, &["k"], &["v"], Fill::Linear).unwrap();
+        let Some(Column::D(v)) = out.get("v") else { panic!("expected D column") };
+        assert_eq!(v, &vec![0.0, 1.0, 3.2, 2.0, 3.0, 8.0]);
+
+        assert!(fill_frame(&df, &[], &["k"], Fill::Forward).is_err());
+        assert!(fill_frame(&df, &["missing"], &["v"], Fill::Forward).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! Serde support for dataframes, enabled by the `serde` feature.
+//!
+//! `enum_df::DataFrame` serializes column oriented by default, as a map of
+//! column name to tagged column, e.g. `{"a": {"I": [1, 2]}}`. The `rows` and
+//! `columns` modules pick an orientation explicitly for use with
+//! `#[serde(with = "...")]`. `dyn_df::DataFrame` serializes columns of the
+//! types registered with `dyn_types::register_serde` (which include the
+//! common primitive types and `String`), anything else is an error.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Error as _, MapAccess, Visitor};
+use serde::ser::{Error as _, SerializeMap, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::dyn_df;
+use crate::dyn_types;
+use crate::enum_df::{Column, DataFrame};
+
+impl Serialize for DataFrame {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.iter())
+    }
+}
+
+/// a map read back with its entries in the order they were written, so
+/// frames keep their column order
+struct Ordered<V>(Vec<(String, V)>);
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for Ordered<V> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct OrderedVisitor<V>(PhantomData<V>);
+
+        impl<'de, V: Deserialize<'de>> Visitor<'de> for OrderedVisitor<V> {
+            type Value = Ordered<V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut m: A) -> Result<Ordered<V>, A::Error> {
+                let mut entries = Vec::with_capacity(m.size_hint().unwrap_or(0));
+                while let Some(e) = m.next_entry()? {
+                    entries.push(e);
+                }
+                Ok(Ordered(entries))
+            }
+        }
+
+        d.deserialize_map(OrderedVisitor(PhantomData))
+    }
+}
+
+impl<'de> Deserialize<'de> for DataFrame {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let Ordered(columns) = Ordered::<Column>::deserialize(d)?;
+        let mut df = DataFrame::new();
+        for (name, c) in columns {
+            df.addcol(&name, c)
+                .map_err(|e| D::Error::custom(format!("column '{}': {}", name, e)))?;
+        }
+        Ok(df)
+    }
+}
+
+/// Column orientation, a map of column name to tagged column. This is the
+/// same as the default `Serialize` impl.
+pub mod columns {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(df: &DataFrame, s: S) -> Result<S::Ok, S::Error> {
+        df.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<DataFrame, D::Error> {
+        DataFrame::deserialize(d)
+    }
+}
+
+/// Row orientation, a list of records mapping column name to value, e.g.
+/// `[{"a": 1, "b": "x"}, {"a": 2, "b": "y"}]`. Values carry no column type, so
+/// on the way back integer columns become `Column::I`, other numeric columns
+/// `Column::D` and strings `Column::S`.
+pub mod rows {
+    use super::*;
+
+    struct Cell<'a>(&'a Column, usize);
+
+    impl Serialize for Cell<'_> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            match self.0 {
+                Column::F(x) => x[self.1].serialize(s),
+                Column::D(x) => x[self.1].serialize(s),
+                Column::I(x) => x[self.1].serialize(s),
+                Column::S(x) => x[self.1].serialize(s),
+                Column::L(l) => {
+                    let (o, v) = (l.offsets(), l.values());
+                    s.collect_seq((o[self.1]..o[self.1 + 1]).map(|i| Cell(v, i)))
+                }
+                Column::R(r) => s.collect_map(r.fields().map(|(n, c)| (n, Cell(c, self.1)))),
+            }
+        }
+    }
+
+    struct Record<'a>(&'a DataFrame, usize);
+
+    impl Serialize for Record<'_> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            let mut m = s.serialize_map(None)?;
+            for (name, c) in self.0.iter() {
+                m.serialize_entry(name, &Cell(c, self.1))?;
+            }
+            m.end()
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        I(i32),
+        D(f64),
+        S(String),
+    }
+
+    fn build_column<E: serde::de::Error>(name: &str, values: Vec<Scalar>) -> Result<Column, E> {
+        if values.iter().all(|v| matches!(v, Scalar::S(_))) {
+            return Ok(Column::S(values.into_iter().map(|v| match v {
+                Scalar::S(x) => x,
+                _ => unreachable!(),
+            }).collect()));
+        }
+        if values.iter().all(|v| matches!(v, Scalar::I(_))) {
+            return Ok(Column::I(values.into_iter().map(|v| match v {
+                Scalar::I(x) => x,
+                _ => unreachable!(),
+            }).collect()));
+        }
+        values.into_iter().map(|v| match v {
+            Scalar::I(x) => Ok(x as f64),
+            Scalar::D(x) => Ok(x),
+            Scalar::S(_) => Err(E::custom(format!("column '{}' mixes string and numeric values", name))),
+        }).collect::<Result<Vec<_>, _>>().map(Column::D)
+    }
+
+    pub fn serialize<S: Serializer>(df: &DataFrame, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq((0..df.len()).map(|row| Record(df, row)))
+    }
+
+    /// columns are in the key order of the first record
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<DataFrame, D::Error> {
+        let records = Vec::<Ordered<Scalar>>::deserialize(d)?;
+        let mut df = DataFrame::new();
+        let Some(first) = records.first() else {
+            return Ok(df);
+        };
+        let names: Vec<String> = first.0.iter().map(|(n, _)| n.clone()).collect();
+        let mut records: Vec<HashMap<String, Scalar>> = records.into_iter().map(|r| r.0.into_iter().collect()).collect();
+        let mut values: Vec<Vec<Scalar>> = names.iter().map(|_| Vec::with_capacity(records.len())).collect();
+        for (i, rec) in records.iter_mut().enumerate() {
+            for (name, v) in names.iter().zip(values.iter_mut()) {
+                let x = rec.remove(name)
+                    .ok_or_else(|| D::Error::custom(format!("record {} is missing column '{}'", i, name)))?;
+                v.push(x);
+            }
+            if let Some(extra) = rec.keys().next() {
+                return Err(D::Error::custom(format!("record {} has unexpected column '{}'", i, extra)));
+            }
+        }
+        for (name, v) in names.iter().zip(values) {
+            df.addcol(name, build_column(name, v)?).expect("records have equal length");
+        }
+        Ok(df)
+    }
+}
+
+fn serialize_dyn<S: Serializer>(col: &dyn dyn_df::Column, s: S) -> Result<S::Ok, S::Error> {
+    let t = dyn_types::lookup(col).ok_or_else(|| S::Error::custom("column type cannot be serialized"))?;
+    let data = t.to_json(col).map_err(S::Error::custom)?;
+    let mut st = s.serialize_struct("Col", 2)?;
+    st.serialize_field("type", t.name)?;
+    st.serialize_field("data", &data)?;
+    st.end()
+}
+
+/// a dyn column before its type name is looked up in the registry
+#[derive(Deserialize)]
+struct TaggedCol {
+    #[serde(rename = "type")]
+    name: String,
+    data: serde_json::Value,
+}
+
+struct DynCol<'a>(&'a dyn dyn_df::Column);
+
+impl Serialize for DynCol<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        serialize_dyn(self.0, s)
+    }
+}
+
+impl Serialize for dyn_df::DataFrame {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.iter().map(|(n, c)| (n, DynCol(c.as_ref()))))
+    }
+}
+
+impl<'de> Deserialize<'de> for dyn_df::DataFrame {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let Ordered(columns) = Ordered::<TaggedCol>::deserialize(d)?;
+        let mut df = dyn_df::DataFrame::new();
+        for (name, c) in columns {
+            let t = dyn_types::lookup_name(&c.name)
+                .ok_or_else(|| D::Error::custom(format!("column '{}': unknown type '{}'", name, c.name)))?;
+            let col = t.from_json(c.data).map_err(|e| D::Error::custom(format!("column '{}': {}", name, e)))?;
+            if df.names().next().is_some() && col.len() != df.len() {
+                return Err(D::Error::custom(format!("column '{}': column lengths don't match", name)));
+            }
+            df.add_ref(name, col);
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+    use crate::dyn_df::AsVec;
+
+    #[derive(Serialize, Deserialize)]
+    struct RowFrame {
+        #[serde(with = "rows")]
+        df: DataFrame,
+    }
+
+    #[test]
+    fn test_column_round_trip() {
+        let df = df! { "a" => [1, 2], "b" => [0.5f32, 1.5], "c" => ["x", "y"] };
+        let s = serde_json::to_string(&df).unwrap();
+        let back: DataFrame = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.len(), 2);
+        let Some(Column::F(b)) = back.get("b") else { panic!("expected F column") };
+        assert_eq!(b, &vec![0.5, 1.5]);
+        assert!(matches!(back.get("c"), Some(Column::S(_))));
+    }
+
+    #[test]
+    fn test_column_order() {
+        let df = df! { "e" => [1], "a" => [2], "d" => ["x"], "b" => [0.5], "c" => [3] };
+        let back: DataFrame = serde_json::from_str(&serde_json::to_string(&df).unwrap()).unwrap();
+        assert_eq!(back, df);
+
+        let s = serde_json::to_string(&RowFrame { df: df.clone() }).unwrap();
+        let back: RowFrame = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.df.names().collect::<Vec<_>>(), vec!["e", "a", "d", "b", "c"]);
+        let reordered = r#"{"df": [{"b": 1, "a": 2}, {"a": 3, "b": 4}]}"#;
+        let back: RowFrame = serde_json::from_str(reordered).unwrap();
+        assert_eq!(back.df, df! { "b" => [1, 4], "a" => [2, 3] });
+    }
+
+    #[test]
+    fn test_column_bad_lens() {
+        let r = serde_json::from_str::<DataFrame>(r#"{"a": {"I": [1, 2]}, "b": {"I": [1]}}"#);
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_nested_checked() {
+        let l = crate::nested::ListColumn::from_lists(vec![vec![1, 2], vec![], vec![3]]);
+        let df = df! { "l" => Column::L(l) };
+        let back: DataFrame = serde_json::from_str(&serde_json::to_string(&df).unwrap()).unwrap();
+        assert_eq!(back, df);
+
+        for bad in [
+            r#"{"l": {"L": {"offsets": [], "values": {"I": []}}}}"#,
+            r#"{"l": {"L": {"offsets": [0, 2, 1], "values": {"I": [1]}}}}"#,
+            r#"{"l": {"L": {"offsets": [0, 5], "values": {"I": [1]}}}}"#,
+            r#"{"r": {"R": {"len": 1, "fields": [["a", {"I": [1]}], ["b", {"I": []}]]}}}"#,
+            r#"{"r": {"R": {"len": 2, "fields": [["a", {"I": [1]}]]}}}"#,
+        ] {
+            assert!(serde_json::from_str::<DataFrame>(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_row_round_trip() {
+        let df = df! { "a" => [1, 2], "b" => [0.5, 1.0], "c" => ["x", "y"] };
+        let s = serde_json::to_string(&RowFrame { df }).unwrap();
+        let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+        assert_eq!(v["df"][1]["c"], "y");
+        assert_eq!(v["df"][0]["a"], 1);
+
+        let back: RowFrame = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.df.len(), 2);
+        assert!(matches!(back.df.get("a"), Some(Column::I(_))));
+        let Some(Column::D(b)) = back.df.get("b") else { panic!("expected D column") };
+        assert_eq!(b, &vec![0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_row_errors() {
+        let missing = r#"{"df": [{"a": 1, "b": 2}, {"a": 3}]}"#;
+        assert!(serde_json::from_str::<RowFrame>(missing).is_err());
+        let mixed = r#"{"df": [{"a": 1}, {"a": "x"}]}"#;
+        let err = serde_json::from_str::<RowFrame>(mixed).err().unwrap();
+        assert!(err.to_string().contains("mixes string and numeric"));
+    }
+
+    #[test]
+    fn test_dyn_round_trip() {
+        let mut df = dyn_df::DataFrame::new();
+        df.add_col("a".to_string(), dyn_df::Col::from_vec(vec![1u64, 2, 3]));
+        df.add_col("b".to_string(), dyn_df::Col::from_vec(vec!["x".to_string(), "y".to_string(), "z".to_string()]));
+        let s = serde_json::to_string(&df).unwrap();
+        let back: dyn_df::DataFrame = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.len(), 3);
+        assert_eq!(back.get_col("a").as_vec::<u64>(), &vec![1, 2, 3]);
+        assert_eq!(back.get_col("b").as_vec::<String>()[2], "z");
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    impl dyn_types::Element for Point {
+        fn fmt_value(&self) -> String {
+            format!("({}, {})", self.x, self.y)
+        }
+
+        fn eq_value(&self, other: &Self) -> bool {
+            (self.x, self.y) == (other.x, other.y)
+        }
+
+        fn hash_value(&self, mut state: &mut dyn std::hash::Hasher) {
+            std::hash::Hash::hash(&(self.x, self.y), &mut state)
+        }
+    }
+
+    #[test]
+    fn test_dyn_registered_type() {
+        dyn_types::register_serde::<Point>("point").unwrap();
+        // registering again without serde keeps the serialization
+        dyn_types::register::<Point>("point").unwrap();
+        let mut df = dyn_df::DataFrame::new();
+        df.add_col("p".to_string(), dyn_df::Col::from_vec(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]));
+        let s = serde_json::to_string(&df).unwrap();
+        assert_eq!(s, r#"{"p":{"type":"point","data":[{"x":1,"y":2},{"x":3,"y":4}]}}"#);
+        let back: dyn_df::DataFrame = serde_json::from_str(&s).unwrap();
+        assert!(back == df);
+        assert!(serde_json::from_str::<dyn_df::DataFrame>(r#"{"p":{"type":"nope","data":[]}}"#).is_err());
+    }
+
+    /// an element type never registered
+    #[derive(Clone)]
+    struct Opaque(i32);
+
+    impl dyn_types::Element for Opaque {
+        fn fmt_value(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn eq_value(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+
+        fn hash_value(&self, mut state: &mut dyn std::hash::Hasher) {
+            std::hash::Hash::hash(&self.0, &mut state)
+        }
+    }
+
+    #[test]
+    fn test_dyn_column_order() {
+        let mut df = dyn_df::DataFrame::new();
+        for n in ["e", "a", "d", "b", "c"] {
+            df.add_col(n.to_string(), dyn_df::Col::from_vec(vec![n.to_string()]));
+        }
+        df.add_col("f".to_string(), dyn_df::Col::from_vec(vec![1.5]));
+        let back: dyn_df::DataFrame = serde_json::from_str(&serde_json::to_string(&df).unwrap()).unwrap();
+        assert_eq!(back.names().collect::<Vec<_>>(), vec!["e", "a", "d", "b", "c", "f"]);
+        assert!(back == df);
+    }
+
+    #[test]
+    fn test_dyn_unknown_type() {
+        let mut df = dyn_df::DataFrame::new();
+        df.add_col("a".to_string(), dyn_df::Col::from_vec(vec![Opaque(1)]));
+        assert!(serde_json::to_string(&df).is_err());
+    }
+}
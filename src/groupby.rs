@@ -0,0 +1,291 @@
+//! Group-by aggregation over `enum_df` frames.
+//!
+//! `GroupBy` holds only one accumulator per group and aggregate, so it can be
+//! fed batch by batch (e.g. from `csv::read_batches`) without keeping the
+//! input around. Groups come out in order of first appearance.
+
+use std::collections::HashMap;
+
+use crate::enum_df::{Column, DataFrame};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Agg {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+}
+
+impl Agg {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Agg::Count => "count",
+            Agg::Sum => "sum",
+            Agg::Mean => "mean",
+            Agg::Min => "min",
+            Agg::Max => "max",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Agg> {
+        match s.to_ascii_lowercase().as_str() {
+            "count" => Some(Agg::Count),
+            "sum" => Some(Agg::Sum),
+            "mean" | "avg" => Some(Agg::Mean),
+            "min" => Some(Agg::Min),
+            "max" => Some(Agg::Max),
+            _ => None,
+        }
+    }
+}
+
+/// A single group key value. Floats are keyed by their bits so they can be
+/// hashed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    F(u32),
+    D(u64),
+    I(i32),
+    S(String),
+}
+
+fn key_at(c: &Column, i: usize) -> Key {
+    match c {
+        Column::F(x) => Key::F(x[i].to_bits()),
+        Column::D(x) => Key::D(x[i].to_bits()),
+        Column::I(x) => Key::I(x[i]),
+        Column::S(x) => Key::S(x[i].clone()),
+        Column::L(_) | Column::R(_) => unreachable!("nested key columns are rejected"),
+    }
+}
+
+/// look up the key columns of a frame, nested columns cannot be keys
+fn key_columns<'a>(df: &'a DataFrame, keys: &[impl AsRef<str>]) -> Result<Vec<&'a Column>, String> {
+    keys.iter().map(|k| match df.get(k.as_ref()) {
+        None => Err(format!("no column named '{}'", k.as_ref())),
+        Some(Column::L(_) | Column::R(_)) => Err(format!("cannot group by nested column '{}'", k.as_ref())),
+        Some(c) => Ok(c),
+    }).collect()
+}
+
+/// numeric value at row `i` as f64, None for string columns
+pub(crate) fn numeric_at(c: &Column, i: usize) -> Option<f64> {
+    match c {
+        Column::F(x) => Some(x[i] as f64),
+        Column::D(x) => Some(x[i]),
+        Column::I(x) => Some(x[i] as f64),
+        _ => None,
+    }
+}
+
+/// row indices of each group of the `keys` columns, groups in order of first
+/// appearance
+pub(crate) fn group_rows(df: &DataFrame, keys: &[&str]) -> Result<Vec<Vec<usize>>, String> {
+    let keys = key_columns(df, keys)?;
+    let mut index: HashMap<Vec<Key>, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for i in 0..df.len() {
+        let key: Vec<Key> = keys.iter().map(|c| key_at(c, i)).collect();
+        let g = *index.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(i);
+    }
+    Ok(groups)
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl State {
+    fn new() -> State {
+        State { count: 0, sum: 0.0, min: f64::INFINITY, max: f64::NEG_INFINITY }
+    }
+
+    fn update(&mut self, v: Option<f64>) {
+        self.count += 1;
+        if let Some(v) = v {
+            self.sum += v;
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+    }
+
+    fn result(&self, agg: Agg) -> f64 {
+        match agg {
+            Agg::Count => self.count as f64,
+            Agg::Sum => self.sum,
+            Agg::Mean => self.sum / self.count as f64,
+            Agg::Min => self.min,
+            Agg::Max => self.max,
+        }
+    }
+}
+
+pub struct GroupBy {
+    keys: Vec<String>,
+    aggs: Vec<(String, Agg)>,
+    key_types: Option<Vec<&'static str>>,
+    index: HashMap<Vec<Key>, usize>,
+    groups: Vec<Vec<Key>>,
+    states: Vec<Vec<State>>,
+}
+
+impl GroupBy {
+    /// group by the `keys` columns computing `aggs`, given as (column, agg).
+    /// Fails if two outputs would have the same name, e.g. a repeated key or
+    /// aggregate, or a key named like an aggregate's `<column>_<agg>`.
+    pub fn new(keys: &[&str], aggs: &[(&str, Agg)]) -> Result<GroupBy, String> {
+        let mut names: Vec<String> = Vec::new();
+        let outs = keys.iter().map(|k| k.to_string()).chain(aggs.iter().map(|(c, a)| format!("{}_{}", c, a.name())));
+        for n in outs {
+            if names.contains(&n) {
+                return Err(format!("group by output column '{}' appears twice", n));
+            }
+            names.push(n);
+        }
+        Ok(GroupBy {
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            aggs: aggs.iter().map(|(c, a)| (c.to_string(), *a)).collect(),
+            key_types: None,
+            index: HashMap::new(),
+            groups: Vec::new(),
+            states: Vec::new(),
+        })
+    }
+
+    pub fn n_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// fold a batch into the running aggregates
+    pub fn update(&mut self, batch: &DataFrame) -> Result<(), String> {
+        let get = |n: &str| batch.get(n).ok_or_else(|| format!("no column named '{}'", n));
+        let keys = key_columns(batch, &self.keys)?;
+        let vals = self.aggs.iter().map(|(c, a)| {
+            let col = get(c)?;
+            if *a != Agg::Count && !col.is_numeric() {
+                return Err(format!("cannot compute {} of non-numeric column '{}'", a.name(), c));
+            }
+            Ok(col)
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        let types: Vec<_> = keys.iter().map(|c| c.type_name()).collect();
+        match &self.key_types {
+            Some(t) if *t != types => return Err("key column types changed between batches".to_string()),
+            Some(_) => (),
+            None => self.key_types = Some(types),
+        }
+
+        for i in 0..batch.len() {
+            let key: Vec<Key> = keys.iter().map(|c| key_at(c, i)).collect();
+            let g = match self.index.get(&key) {
+                Some(g) => *g,
+                None => {
+                    self.index.insert(key.clone(), self.groups.len());
+                    self.groups.push(key);
+                    self.states.push(vec![State::new(); self.aggs.len()]);
+                    self.groups.len() - 1
+                }
+            };
+            for (s, c) in self.states[g].iter_mut().zip(&vals) {
+                s.update(numeric_at(c, i));
+            }
+        }
+        Ok(())
+    }
+
+    /// one row per group: the key columns followed by a `<column>_<agg>`
+    /// column per aggregate, i32 for counts and f64 otherwise
+    pub fn finish(&self) -> DataFrame {
+        let mut df = DataFrame::new();
+        let types = self.key_types.clone().unwrap_or_default();
+        for (k, (name, t)) in self.keys.iter().zip(types).enumerate() {
+            let vals = self.groups.iter().map(|g| &g[k]);
+            let col = match t {
+                "f32" => Column::F(vals.map(|v| match v { Key::F(b) => f32::from_bits(*b), _ => unreachable!() }).collect()),
+                "f64" => Column::D(vals.map(|v| match v { Key::D(b) => f64::from_bits(*b), _ => unreachable!() }).collect()),
+                "i32" => Column::I(vals.map(|v| match v { Key::I(x) => *x, _ => unreachable!() }).collect()),
+                _ => Column::S(vals.map(|v| match v { Key::S(x) => x.clone(), _ => unreachable!() }).collect()),
+            };
+            df.addcol(name, col).expect("one key per group");
+        }
+        for (a, (name, agg)) in self.aggs.iter().enumerate() {
+            let col = match agg {
+                Agg::Count => Column::I(self.states.iter().map(|s| s[a].count as i32).collect()),
+                _ => Column::D(self.states.iter().map(|s| s[a].result(*agg)).collect()),
+            };
+            df.addcol(&format!("{}_{}", name, agg.name()), col).expect("one state per group");
+        }
+        df
+    }
+}
+
+/// group an in-memory frame, see `GroupBy`
+pub fn group_by(df: &DataFrame, keys: &[&str], aggs: &[(&str, Agg)]) -> Result<DataFrame, String> {
+    let mut g = GroupBy::new(keys, aggs)?;
+    g.update(df)?;
+    Ok(g.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+
+    #[test]
+    fn test_group_by() {
+        let df = df! {
+            "k" => ["a", "b", "a", "c", "b"],
+            "v" => [1, 2, 3, 4, 5],
+        };
+        let out = group_by(&df, &["k"], &[("v", Agg::Sum), ("v", Agg::Count), ("v", Agg::Mean)]).unwrap();
+        assert_eq!(out.len(), 3);
+        let names: Vec<_> = out.names().collect();
+        assert_eq!(names, vec!["k", "v_sum", "v_count", "v_mean"]);
+        let Some(Column::S(k)) = out.get("k") else { panic!("expected S column") };
+        assert_eq!(k, &vec!["a", "b", "c"]);
+        let Some(Column::D(s)) = out.get("v_sum") else { panic!("expected D column") };
+        assert_eq!(s, &vec![4.0, 7.0, 4.0]);
+        let Some(Column::I(c)) = out.get("v_count") else { panic!("expected I column") };
+        assert_eq!(c, &vec![2, 2, 1]);
+        let Some(Column::D(m)) = out.get("v_mean") else { panic!("expected D column") };
+        assert_eq!(m, &vec![2.0, 3.5, 4.0]);
+    }
+
+    #[test]
+    fn test_group_by_batches() {
+        let mut g = GroupBy::new(&["k"], &[("v", Agg::Min), ("v", Agg::Max)]).unwrap();
+        g.update(&df! { "k" => [1, 2], "v" => [1.0, 5.0] }).unwrap();
+        g.update(&df! { "k" => [1, 1], "v" => [-2.0, 7.0] }).unwrap();
+        assert_eq!(g.n_groups(), 2);
+        assert!(g.update(&df! { "k" => ["x"], "v" => [1.0] }).is_err());
+
+        let out = g.finish();
+        let Some(Column::I(k)) = out.get("k") else { panic!("expected I column") };
+        assert_eq!(k, &vec![1, 2]);
+        let Some(Column::D(mn)) = out.get("v_min") else { panic!("expected D column") };
+        assert_eq!(mn, &vec![-2.0, 5.0]);
+        let Some(Column::D(mx)) = out.get("v_max") else { panic!("expected D column") };
+        assert_eq!(mx, &vec![7.0, 5.0]);
+    }
+
+    #[test]
+    fn test_group_by_errors() {
+        let df = df! { "k" => ["a"], "s" => ["x"] };
+        assert!(group_by(&df, &["missing"], &[]).is_err());
+        assert!(group_by(&df, &["k"], &[("s", Agg::Sum)]).is_err());
+        assert!(group_by(&df, &["k"], &[("s", Agg::Count)]).is_ok());
+        // outputs sharing a name would replace one another
+        assert!(group_by(&df, &["k", "k"], &[]).is_err());
+        assert!(group_by(&df, &["k"], &[("s", Agg::Count), ("s", Agg::Count)]).is_err());
+        assert!(GroupBy::new(&["s_count"], &[("s", Agg::Count)]).is_err());
+    }
+}
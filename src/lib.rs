@@ -1,5 +1,30 @@
+// lets the derive macros refer to `::rusttest` from inside this crate too
+extern crate self as rusttest;
+
 pub mod enum_df;
 pub mod compute;
 pub mod dyn_df;
+pub mod dyn_types;
 pub mod tree;
-pub mod rl;
\ No newline at end of file
+pub mod rl;
+pub mod rows;
+pub mod chunked;
+pub mod csv;
+pub mod groupby;
+pub mod fill;
+pub mod nested;
+pub mod sample;
+pub mod compare;
+pub mod sketch;
+pub mod encode;
+pub mod preprocess;
+pub mod split;
+pub mod sql;
+pub mod dyn_agg;
+pub mod numeric;
+pub mod pool;
+// the mapped format is read in place, so only native on little endian targets
+#[cfg(target_endian = "little")]
+pub mod colfile;
+#[cfg(feature = "serde")]
+pub mod frame_serde;
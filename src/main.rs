@@ -1,19 +1,718 @@
-use rusttest::compute::process_array_t;
-use rusttest::enum_df::{Column, DataFrame};
+// `rusty`: command line tool and interactive shell for enum_df frames
 
-pub fn main() {
-    let mut df = DataFrame::new();
-    df.addcol("col1", Column::F(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0])).unwrap();
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-    // mutable borrow of array so cannot have immutable borrow live past here
-    if let Column::F(c) = df.get_mut("col1").unwrap() {
-        process_array_t(c);
+use rusttest::csv::{self, CsvOptions};
+use rusttest::enum_df::DataFrame;
+use rusttest::sql::SqlContext;
+
+const USAGE: &str = "\
+usage: rusty [COMMAND] [OPTIONS] [FILE]
+
+Reads FILE, or stdin if FILE is missing or `-`, and writes the result to stdout.
+File formats follow the extension: .csv, .tsv, .json or .col.
+
+commands:
+  head [-n N]                    first N rows, 10 by default
+  tail [-n N]                    last N rows, 10 by default
+  schema                         column names and types
+  sort --by 'COL [desc], ...'    sort by one or more columns
+  filter --expr EXPR             keep rows where a SQL expression is true
+  groupby --by COLS --agg AGGS   aggregate, e.g. --by sym --agg 'sum(qty), count(*)'
+  convert IN OUT                 convert between file formats, `-` for stdin/stdout
+  repl                           interactive shell, the default with no command
+
+options:
+  -o, --output FILE  write the result to FILE instead of stdout
+  --from FMT         format of stdin: csv, tsv or json (default csv)
+  --to FMT           format of stdout: csv, tsv, json or table (default csv)";
+
+const HELP: &str = "\
+commands:
+  load <file> [as <name>]    read a .csv, .tsv, .json or .col file and make it current
+  use <name>                 make a loaded table current
+  tables                     list loaded tables
+  schema                     column names and types of the current table
+  head [n], tail [n]         first or last n rows, 10 by default
+  select <col>, ...          keep only the given columns
+  filter <expr>              keep rows where a SQL expression is true
+  sort <col> [desc], ...     sort by one or more columns
+  groupby <cols> agg <aggs>  aggregate, e.g. `groupby sym agg sum(qty), count(*)`
+  sql <query>                run a SELECT over the loaded tables
+  save <file>                write the current table as .csv, .tsv, .json or .col
+  history                    list previous commands, `!n` runs command n again
+  help, quit
+select, filter, sort, groupby and sql store their result as table `_` and make it current";
+
+/// table that command results are stored under
+const RESULT: &str = "_";
+
+// history is kept across sessions in this file under $HOME
+const HISTORY_FILE: &str = ".rusty_history";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Csv,
+    Tsv,
+    Json,
+    Col,
+    Table,
+}
+
+impl Format {
+    fn parse(s: &str) -> Result<Format, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "json" => Ok(Format::Json),
+            "col" => Ok(Format::Col),
+            "table" => Ok(Format::Table),
+            f => Err(format!("unknown format '{}'", f)),
+        }
+    }
+
+    /// format of a file, from its extension
+    fn of_path(path: &str) -> Result<Format, String> {
+        let ext = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+        match Format::parse(ext) {
+            Ok(Format::Table) | Err(_) => Err(format!("{}: unsupported file type", path)),
+            f => f,
+        }
+    }
+
+    fn csv_options(&self) -> CsvOptions {
+        let delimiter = if *self == Format::Tsv { b'\t' } else { b',' };
+        CsvOptions { delimiter, ..CsvOptions::default() }
+    }
+}
+
+fn unsupported(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, msg)
+}
+
+#[cfg(feature = "serde")]
+fn read_json<R: BufRead>(r: R) -> io::Result<DataFrame> {
+    let mut de = serde_json::Deserializer::from_reader(r);
+    Ok(rusttest::frame_serde::rows::deserialize(&mut de)?)
+}
+
+#[cfg(feature = "serde")]
+fn write_json<W: Write>(df: &DataFrame, mut w: W) -> io::Result<()> {
+    rusttest::frame_serde::rows::serialize(df, &mut serde_json::Serializer::new(&mut w))?;
+    writeln!(w)
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_json<R: BufRead>(_: R) -> io::Result<DataFrame> {
+    Err(unsupported("JSON needs rusty built with the `serde` feature"))
+}
+
+#[cfg(not(feature = "serde"))]
+fn write_json<W: Write>(_: &DataFrame, _: W) -> io::Result<()> {
+    Err(unsupported("JSON needs rusty built with the `serde` feature"))
+}
+
+fn read_frame<R: BufRead>(r: R, fmt: Format) -> io::Result<DataFrame> {
+    match fmt {
+        Format::Csv | Format::Tsv => csv::read_csv(r, fmt.csv_options()),
+        Format::Json => read_json(r),
+        Format::Col | Format::Table => Err(unsupported("format cannot be read from a stream")),
+    }
+}
+
+fn write_frame<W: Write>(df: &DataFrame, mut w: W, fmt: Format) -> io::Result<()> {
+    match fmt {
+        Format::Csv | Format::Tsv => csv::write_csv(df, &mut w, fmt.csv_options().delimiter)?,
+        Format::Json => write_json(df, &mut w)?,
+        Format::Table => writeln!(w, "{}", df.view())?,
+        Format::Col => return Err(unsupported("format cannot be written to a stream")),
     }
+    w.flush()
+}
+
+fn load_frame(path: &str) -> Result<DataFrame, String> {
+    let fmt = Format::of_path(path)?;
+    let df = match fmt {
+        #[cfg(target_endian = "little")]
+        Format::Col => rusttest::colfile::MappedFrame::open(path).and_then(|m| {
+            let names: Vec<String> = m.schema().map(|(n, _)| n.to_string()).collect();
+            m.to_frame(&names.iter().map(String::as_str).collect::<Vec<_>>())
+        }),
+        _ => File::open(path).and_then(|f| read_frame(BufReader::new(f), fmt)),
+    };
+    df.map_err(|e| format!("{}: {}", path, e))
+}
 
-    // now we can do the mutable borrow, and this can be used
-    let Column::F(c) = df.get("col1").unwrap() else {
-        panic!("failed to get expected F array");
+fn save_frame(df: &DataFrame, path: &str) -> Result<(), String> {
+    let fmt = Format::of_path(path)?;
+    let r = match fmt {
+        #[cfg(target_endian = "little")]
+        Format::Col => rusttest::colfile::write_frame(df, path),
+        _ => File::create(path).and_then(|f| write_frame(df, BufWriter::new(f), fmt)),
     };
-    // we can still use it since it is in the outer scope of let
-    println!("after process_array: {:?}", c);
+    r.map_err(|e| format!("{}: {}", path, e))
+}
+
+/// quote a name for use as a SQL identifier
+fn ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn filter_sql(table: &str, expr: &str) -> String {
+    format!("SELECT * FROM {} WHERE {}", ident(table), expr)
+}
+
+/// query sorting `table` by a spec like `a desc, b`
+fn sort_sql(table: &str, spec: &str) -> Result<String, String> {
+    let keys = spec.split(',')
+        .map(|k| match k.split_whitespace().collect::<Vec<_>>()[..] {
+            [c] => Ok(ident(c)),
+            [c, d] if d.eq_ignore_ascii_case("asc") || d.eq_ignore_ascii_case("desc") => {
+                Ok(format!("{} {}", ident(c), d))
+            }
+            _ => Err(format!("invalid sort key '{}', expected `<col> [asc|desc]`", k.trim())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("SELECT * FROM {} ORDER BY {}", ident(table), keys.join(", ")))
+}
+
+/// query grouping `table` by comma separated key columns
+fn groupby_sql(table: &str, keys: &str, aggs: &str) -> Result<String, String> {
+    let keys = keys.split(',')
+        .map(|k| match k.trim() {
+            "" => Err(format!("invalid group key list '{}'", keys)),
+            k => Ok(ident(k)),
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", ");
+    Ok(format!("SELECT {}, {} FROM {} GROUP BY {}", keys, aggs, ident(table), keys))
+}
+
+fn schema(name: &str, df: &DataFrame) -> String {
+    let width = df.names().map(str::len).max().unwrap_or(0);
+    let mut out = format!("{}: {} rows", name, df.len());
+    for (n, c) in df.iter() {
+        out.push_str(&format!("\n  {:<w$}  {}", n, c.type_name(), w = width));
+    }
+    out
+}
+
+#[derive(Default)]
+struct Session {
+    ctx: SqlContext,
+    current: Option<String>,
+    history: Vec<String>,
+}
+
+impl Session {
+    fn current(&self) -> Result<(&str, &DataFrame), String> {
+        let name = self.current.as_deref().ok_or("no table loaded, see `help`")?;
+        Ok((name, self.ctx.table(name).unwrap()))
+    }
+
+    fn set_result(&mut self, df: DataFrame) -> String {
+        let out = df.to_string();
+        self.ctx.register(RESULT, df);
+        self.current = Some(RESULT.to_string());
+        out
+    }
+
+    fn query(&mut self, sql: &str) -> Result<String, String> {
+        let df = self.ctx.execute(sql).map_err(|e| e.to_string())?;
+        Ok(self.set_result(df))
+    }
+
+    fn rows_arg(arg: &str) -> Result<usize, String> {
+        if arg.is_empty() {
+            return Ok(10);
+        }
+        arg.parse().map_err(|_| format!("expected a row count, found '{}'", arg))
+    }
+
+    /// run one command line, returning the text to print
+    fn run(&mut self, line: &str) -> Result<String, String> {
+        let (cmd, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arg = arg.trim();
+        match cmd.to_ascii_lowercase().as_str() {
+            "help" => Ok(HELP.to_string()),
+            "load" => {
+                let (path, name) = match arg.rsplit_once(" as ") {
+                    Some((p, n)) => (p.trim(), n.trim().to_string()),
+                    None => {
+                        let stem = Path::new(arg).file_stem().and_then(|s| s.to_str()).unwrap_or(arg);
+                        (arg, stem.to_string())
+                    }
+                };
+                if path.is_empty() {
+                    return Err("usage: load <file> [as <name>]".to_string());
+                }
+                let df = load_frame(path)?;
+                let out = format!("loaded '{}': {} rows x {} columns", name, df.len(), df.names().count());
+                self.ctx.register(&name, df);
+                self.current = Some(name);
+                Ok(out)
+            }
+            "use" => {
+                if self.ctx.table(arg).is_none() {
+                    return Err(format!("no table named '{}'", arg));
+                }
+                self.current = Some(arg.to_string());
+                Ok(String::new())
+            }
+            "tables" => Ok(self.ctx.tables().iter()
+                .map(|t| {
+                    let df = self.ctx.table(t).unwrap();
+                    let mark = if self.current.as_deref() == Some(*t) { "*" } else { " " };
+                    format!("{} {} ({} rows)", mark, t, df.len())
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "schema" => {
+                let (name, df) = self.current()?;
+                Ok(schema(name, df))
+            }
+            "head" => {
+                let n = Self::rows_arg(arg)?;
+                Ok(self.current()?.1.head(n).to_string())
+            }
+            "tail" => {
+                let n = Self::rows_arg(arg)?;
+                Ok(self.current()?.1.tail(n).to_string())
+            }
+            "select" => {
+                let cols: Vec<&str> = arg.split(',').map(str::trim).filter(|c| !c.is_empty()).collect();
+                let df = self.current()?.1.select(&cols)?;
+                Ok(self.set_result(df))
+            }
+            "filter" => {
+                let sql = filter_sql(self.current()?.0, arg);
+                self.query(&sql)
+            }
+            "sort" => {
+                let sql = sort_sql(self.current()?.0, arg)?;
+                self.query(&sql)
+            }
+            "groupby" => {
+                let Some((keys, aggs)) = arg.split_once(" agg ") else {
+                    return Err("usage: groupby <cols> agg <aggs>".to_string());
+                };
+                let sql = groupby_sql(self.current()?.0, keys, aggs)?;
+                self.query(&sql)
+            }
+            "sql" => self.query(arg),
+            "save" => {
+                if arg.is_empty() {
+                    return Err("usage: save <file>".to_string());
+                }
+                let (name, df) = self.current()?;
+                save_frame(df, arg)?;
+                Ok(format!("wrote '{}' to {}", name, arg))
+            }
+            "history" => Ok(self.history.iter()
+                .enumerate()
+                .map(|(i, h)| format!("{:>4}  {}", i + 1, h))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            _ => Err(format!("unknown command '{}', see `help`", cmd)),
+        }
+    }
+
+    /// expand a `!n` history reference
+    fn expand(&self, line: &str) -> Result<String, String> {
+        let Some(n) = line.strip_prefix('!') else {
+            return Ok(line.to_string());
+        };
+        n.parse::<usize>().ok()
+            .and_then(|n| self.history.get(n.wrapping_sub(1)))
+            .cloned()
+            .ok_or_else(|| format!("no command {} in history", line))
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|h| PathBuf::from(h).join(HISTORY_FILE))
+}
+
+fn repl() {
+    let mut session = Session::default();
+    let path = history_path();
+    if let Some(f) = path.as_ref().and_then(|p| File::open(p).ok()) {
+        session.history = BufReader::new(f).lines().map_while(Result::ok).collect();
+    }
+    let mut history_file = path.and_then(|p| OpenOptions::new().create(true).append(true).open(p).ok());
+
+    println!("type `help` for commands");
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else { break };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "quit" || line == "exit" {
+            break;
+        }
+        let line = match session.expand(line) {
+            Ok(l) if l != line => {
+                println!("{}", l);
+                l
+            }
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                continue;
+            }
+        };
+        if let Some(f) = history_file.as_mut() {
+            // losing history is not worth stopping the session for
+            let _ = writeln!(f, "{}", line);
+        }
+        session.history.push(line.clone());
+        match session.run(&line) {
+            Ok(out) if out.is_empty() => (),
+            Ok(out) => println!("{}", out),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
+
+/// Options and positional arguments of a subcommand
+struct Args {
+    positional: Vec<String>,
+    opts: HashMap<String, String>,
+}
+
+impl Args {
+    /// parse `--name value`, `--name=value` and the short forms `-n` and `-o`,
+    /// accepting only the common options and `extra`
+    fn parse(args: &[String], extra: &[&str]) -> Result<Args, String> {
+        let mut out = Args { positional: Vec::new(), opts: HashMap::new() };
+        let mut it = args.iter();
+        while let Some(a) = it.next() {
+            let (name, value) = match a.as_str() {
+                "-n" => ("n", None),
+                "-o" => ("output", None),
+                a if a.starts_with("--") => match a[2..].split_once('=') {
+                    Some((n, v)) => (n, Some(v.to_string())),
+                    None => (&a[2..], None),
+                },
+                a if a.starts_with('-') && a != "-" => return Err(format!("unknown option '{}'", a)),
+                _ => {
+                    out.positional.push(a.clone());
+                    continue;
+                }
+            };
+            if !["output", "from", "to"].contains(&name) && !extra.contains(&name) {
+                return Err(format!("unknown option '{}'", a));
+            }
+            let value = match value {
+                Some(v) => v,
+                None => it.next().ok_or_else(|| format!("option '{}' needs a value", a))?.clone(),
+            };
+            out.opts.insert(name.to_string(), value);
+        }
+        Ok(out)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.opts.get(name).map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.get(name).ok_or_else(|| format!("missing required option --{}", name))
+    }
+
+    fn format(&self, name: &str) -> Result<Format, String> {
+        self.get(name).map_or(Ok(Format::Csv), Format::parse)
+    }
+}
+
+/// Where a subcommand reads its input and writes its result, `None` or `-`
+/// meaning stdin or stdout
+struct Streams {
+    input: Option<String>,
+    output: Option<String>,
+    from: Format,
+    to: Format,
+}
+
+fn is_std(path: &Option<String>) -> bool {
+    path.as_deref().is_none_or(|p| p == "-")
+}
+
+impl Streams {
+    fn new(args: &Args, input: Option<&String>, output: Option<&String>) -> Result<Streams, String> {
+        Ok(Streams {
+            input: input.cloned(),
+            output: output.or(args.opts.get("output")).cloned(),
+            from: args.format("from")?,
+            to: args.format("to")?,
+        })
+    }
+
+    fn read(&self) -> Result<DataFrame, String> {
+        match &self.input {
+            p if is_std(p) => read_frame(io::stdin().lock(), self.from).map_err(|e| format!("stdin: {}", e)),
+            Some(p) => load_frame(p),
+            None => unreachable!(),
+        }
+    }
+
+    /// first `n` rows, reading only as much of a CSV input as needed
+    fn read_head(&self, n: usize) -> Result<DataFrame, String> {
+        let fmt = match &self.input {
+            p if is_std(p) => self.from,
+            Some(p) => Format::of_path(p)?,
+            None => unreachable!(),
+        };
+        if !matches!(fmt, Format::Csv | Format::Tsv) {
+            return Ok(self.read()?.head(n).to_frame());
+        }
+        let opts = CsvOptions { batch_size: n.max(1), ..fmt.csv_options() };
+        let first = |r: Box<dyn BufRead>| {
+            let mut batches = csv::read_batches(r, opts)?;
+            let empty = batches.empty_frame();
+            batches.next().unwrap_or(Ok(empty)).map(|df| df.head(n).to_frame())
+        };
+        match &self.input {
+            p if is_std(p) => first(Box::new(io::stdin().lock())).map_err(|e| format!("stdin: {}", e)),
+            Some(p) => File::open(p)
+                .and_then(|f| first(Box::new(BufReader::new(f))))
+                .map_err(|e| format!("{}: {}", p, e)),
+            None => unreachable!(),
+        }
+    }
+
+    fn write(&self, df: &DataFrame) -> Result<(), String> {
+        match &self.output {
+            p if is_std(p) => match write_frame(df, io::stdout().lock(), self.to) {
+                // the reader went away, e.g. piped into `head`
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                r => r.map_err(|e| format!("stdout: {}", e)),
+            },
+            Some(p) => save_frame(df, p),
+            None => unreachable!(),
+        }
+    }
+
+    /// write a line of text, as `write` does a frame
+    fn write_text(&self, text: &str) -> Result<(), String> {
+        match &self.output {
+            p if is_std(p) => match writeln!(io::stdout().lock(), "{}", text) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                r => r.map_err(|e| format!("stdout: {}", e)),
+            },
+            Some(p) => std::fs::write(p, format!("{}\n", text)).map_err(|e| format!("{}: {}", p, e)),
+            None => unreachable!(),
+        }
+    }
+}
+
+/// run a query against the input registered as table `input`
+fn query_input(df: DataFrame, sql: impl FnOnce(&str) -> Result<String, String>) -> Result<DataFrame, String> {
+    let mut ctx = SqlContext::new();
+    ctx.register("input", df);
+    ctx.execute(&sql("input")?).map_err(|e| e.to_string())
+}
+
+fn run_command(cmd: &str, args: &[String]) -> Result<(), String> {
+    let extra: &[&str] = match cmd {
+        "head" | "tail" => &["n"],
+        "sort" => &["by"],
+        "filter" => &["expr"],
+        "groupby" => &["by", "agg"],
+        "schema" | "convert" => &[],
+        _ => return Err(format!("unknown command '{}', see `rusty --help`", cmd)),
+    };
+    let args = Args::parse(args, extra)?;
+    let max_positional = if cmd == "convert" { 2 } else { 1 };
+    if args.positional.len() > max_positional {
+        return Err(format!("unexpected argument '{}'", args.positional[max_positional]));
+    }
+    let mut positional = args.positional.iter();
+    let streams = Streams::new(&args, positional.next(), positional.next())?;
+    let rows = || args.get("n").map_or(Ok(10), |n| n.parse().map_err(|_| format!("invalid row count '{}'", n)));
+
+    let df = match cmd {
+        "head" => streams.read_head(rows()?)?,
+        "tail" => streams.read()?.tail(rows()?).to_frame(),
+        "schema" => {
+            let name = streams.input.as_deref().filter(|p| *p != "-").unwrap_or("stdin");
+            return streams.write_text(&schema(name, &streams.read()?));
+        }
+        "sort" => query_input(streams.read()?, |t| sort_sql(t, args.required("by")?))?,
+        "filter" => query_input(streams.read()?, |t| Ok(filter_sql(t, args.required("expr")?)))?,
+        "groupby" => query_input(streams.read()?, |t| groupby_sql(t, args.required("by")?, args.required("agg")?))?,
+        "convert" => {
+            if args.positional.len() < 2 {
+                return Err("usage: rusty convert IN OUT".to_string());
+            }
+            streams.read()?
+        }
+        _ => unreachable!(),
+    };
+    streams.write(&df)
+}
+
+pub fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((cmd, rest)) = args.split_first() else {
+        repl();
+        return ExitCode::SUCCESS;
+    };
+    let result = match cmd.as_str() {
+        "repl" => {
+            repl();
+            Ok(())
+        }
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        cmd => run_command(cmd, rest),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("rusty: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> TempPath {
+            TempPath(std::env::temp_dir().join(format!("rusttest-main-{}-{}", std::process::id(), name)))
+        }
+
+        fn as_str(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_session() {
+        let input = TempPath::new("trades.csv");
+        std::fs::write(&input.0, "sym,qty,px\na,10,1.5\nb,20,2.5\na,30,3.5\n").unwrap();
+
+        let mut s = Session::default();
+        assert!(s.run("schema").is_err());
+        let out = s.run(&format!("load {} as trades", input.as_str())).unwrap();
+        assert_eq!(out, "loaded 'trades': 3 rows x 3 columns");
+        assert!(s.run("schema").unwrap().contains("  qty  i32"));
+        assert_eq!(s.run("head 1").unwrap().lines().count(), 3);
+
+        s.run("filter qty > 10").unwrap();
+        assert_eq!(s.current().unwrap().1.len(), 2);
+        s.run("use trades").unwrap();
+        let out = s.run("groupby sym agg sum(qty) AS total").unwrap();
+        assert!(out.contains("total") && out.contains(" 40 "));
+        s.run("use trades").unwrap();
+        s.run("sort qty desc").unwrap();
+        assert!(s.run("head 1").unwrap().contains(" 30 "));
+
+        let output = TempPath::new("sorted.csv");
+        s.run(&format!("save {}", output.as_str())).unwrap();
+        let text = std::fs::read_to_string(&output.0).unwrap();
+        assert_eq!(text.lines().nth(1), Some("a,30,3.5"));
+
+        assert!(s.run("load missing.parquet").is_err());
+        assert!(s.run("frobnicate").is_err());
+    }
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let a = Args::parse(&args(&["-n", "5", "in.csv", "--to=table"]), &["n"]).unwrap();
+        assert_eq!(a.positional, vec!["in.csv"]);
+        assert_eq!(a.get("n"), Some("5"));
+        assert_eq!(a.format("to").unwrap(), Format::Table);
+        assert_eq!(a.format("from").unwrap(), Format::Csv);
+        assert!(Args::parse(&args(&["--by", "x"]), &["n"]).is_err());
+        assert!(Args::parse(&args(&["--expr"]), &["expr"]).is_err());
+        assert!(Args::parse(&args(&["-x"]), &[]).is_err());
+        assert!(Args::parse(&args(&["-"]), &[]).unwrap().positional == vec!["-"]);
+    }
+
+    #[test]
+    fn test_sort_sql() {
+        assert_eq!(sort_sql("t", "a desc, b").unwrap(), "SELECT * FROM \"t\" ORDER BY \"a\" desc, \"b\"");
+        assert!(sort_sql("t", "a sideways").is_err());
+        assert_eq!(
+            groupby_sql("t", "a, order", "count(*)").unwrap(),
+            "SELECT \"a\", \"order\", count(*) FROM \"t\" GROUP BY \"a\", \"order\""
+        );
+        assert!(groupby_sql("t", "a,,b", "count(*)").is_err());
+    }
+
+    #[test]
+    fn test_commands() {
+        let input = TempPath::new("cli.csv");
+        std::fs::write(&input.0, "sym,qty\na,10\nb,20\na,30\n").unwrap();
+        let output = TempPath::new("cli.tsv");
+        let run = |cmd: &str, a: &[&str]| {
+            let mut a = args(a);
+            a.extend(args(&[input.as_str(), "-o", output.as_str()]));
+            run_command(cmd, &a)?;
+            Ok::<_, String>(std::fs::read_to_string(&output.0).unwrap())
+        };
+
+        assert_eq!(run("head", &["-n", "2"]).unwrap(), "sym\tqty\na\t10\nb\t20\n");
+        assert_eq!(run("tail", &["-n", "1"]).unwrap(), "sym\tqty\na\t30\n");
+        assert!(run("schema", &[]).unwrap().ends_with("  qty  i32\n"));
+        assert_eq!(run("sort", &["--by", "qty desc"]).unwrap().lines().nth(1), Some("a\t30"));
+        assert_eq!(run("filter", &["--expr", "sym = 'b'"]).unwrap(), "sym\tqty\nb\t20\n");
+        let out = run("groupby", &["--by", "sym", "--agg", "count(*) AS n"]).unwrap();
+        assert_eq!(out, "sym\tn\na\t2\nb\t1\n");
+
+        assert!(run("filter", &[]).is_err());
+        assert!(run("frobnicate", &[]).is_err());
+        assert!(run_command("head", &args(&["a.csv", "b.csv"])).is_err());
+
+        run_command("convert", &args(&[input.as_str(), output.as_str()])).unwrap();
+        assert_eq!(load_frame(output.as_str()).unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_convert_json() {
+        let input = TempPath::new("json.csv");
+        std::fs::write(&input.0, "qty\n1\n2\n").unwrap();
+        let json = TempPath::new("out.json");
+        run_command("convert", &args(&[input.as_str(), json.as_str()])).unwrap();
+        assert_eq!(std::fs::read_to_string(&json.0).unwrap(), "[{\"qty\":1},{\"qty\":2}]\n");
+        assert_eq!(load_frame(json.as_str()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut s = Session::default();
+        s.history.push("tables".to_string());
+        assert_eq!(s.expand("!1").unwrap(), "tables");
+        assert_eq!(s.expand("head").unwrap(), "head");
+        assert!(s.expand("!2").is_err());
+        assert!(s.expand("!0").is_err());
+    }
 }
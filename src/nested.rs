@@ -0,0 +1,356 @@
+//! Nested column types: lists and structs.
+//!
+//! A `ListColumn` stores the elements of every row back to back in a single
+//! child column, row `i` holding `values[offsets[i]..offsets[i + 1]]`. A
+//! `StructColumn` holds named child columns of equal length, row `i` being the
+//! `i`th value of each field. Both nest, e.g. a list of structs.
+
+use crate::chunked;
+use crate::enum_df::{Column, ColumnView, DataFrame};
+use crate::groupby;
+use crate::rows::ColumnType;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawListColumn"))]
+pub struct ListColumn {
+    offsets: Vec<usize>,
+    values: Box<Column>,
+}
+
+/// a deserialized list column before `ListColumn::new` checks it
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawListColumn {
+    offsets: Vec<usize>,
+    values: Box<Column>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawListColumn> for ListColumn {
+    type Error = &'static str;
+
+    fn try_from(r: RawListColumn) -> Result<ListColumn, &'static str> {
+        ListColumn::new(r.offsets, *r.values)
+    }
+}
+
+impl ListColumn {
+    /// `offsets` has one more entry than there are rows, starting at 0 and
+    /// ending at the number of values
+    pub fn new(offsets: Vec<usize>, values: Column) -> Result<ListColumn, &'static str> {
+        if offsets.first() != Some(&0) {
+            return Err("list offsets must start at 0");
+        }
+        if offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err("list offsets must not decrease");
+        }
+        if offsets.last() != Some(&values.len()) {
+            return Err("last list offset must equal the number of values");
+        }
+        Ok(ListColumn { offsets, values: Box::new(values) })
+    }
+
+    pub fn from_lists<T: ColumnType>(lists: Vec<Vec<T>>) -> ListColumn {
+        let mut offsets = vec![0];
+        offsets.extend(lists.iter().scan(0, |end, l| {
+            *end += l.len();
+            Some(*end)
+        }));
+        let values = T::into_column(lists.into_iter().flatten().collect());
+        ListColumn { offsets, values: Box::new(values) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// the child column holding the elements of all rows
+    pub fn values(&self) -> &Column {
+        &self.values
+    }
+
+    /// elements of row `i`
+    pub fn get(&self, i: usize) -> ColumnView<'_> {
+        self.values.slice(self.offsets[i], self.offsets[i + 1] - self.offsets[i])
+    }
+
+    /// number of elements in each row
+    pub fn lengths(&self) -> Vec<usize> {
+        self.offsets.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    pub(crate) fn take(&self, idx: &[usize]) -> ListColumn {
+        let mut offsets = vec![0];
+        let mut child = Vec::new();
+        for i in idx {
+            child.extend(self.offsets[*i]..self.offsets[*i + 1]);
+            offsets.push(child.len());
+        }
+        ListColumn { offsets, values: Box::new(self.values.take(&child)) }
+    }
+
+    /// copy out the rows covered by a window of `offsets` into `values`,
+    /// rebasing the offsets to start at 0
+    pub(crate) fn slice(offsets: &[usize], values: &Column) -> ListColumn {
+        let start = offsets[0];
+        let end = offsets[offsets.len() - 1];
+        ListColumn {
+            offsets: offsets.iter().map(|o| o - start).collect(),
+            values: Box::new(values.slice(start, end - start).to_column()),
+        }
+    }
+
+    pub(crate) fn concat(lists: Vec<ListColumn>) -> ListColumn {
+        let mut offsets = vec![0];
+        let mut values = Vec::with_capacity(lists.len());
+        for l in lists {
+            let base = offsets[offsets.len() - 1];
+            offsets.extend(l.offsets[1..].iter().map(|o| o + base));
+            values.push(*l.values);
+        }
+        let n = offsets[offsets.len() - 1];
+        ListColumn { offsets, values: Box::new(chunked::concat(values, n)) }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawStructColumn"))]
+pub struct StructColumn {
+    len: usize,
+    fields: Vec<(String, Column)>,
+}
+
+/// a deserialized struct column before `StructColumn::new` checks it
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawStructColumn {
+    len: usize,
+    fields: Vec<(String, Column)>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawStructColumn> for StructColumn {
+    type Error = &'static str;
+
+    fn try_from(r: RawStructColumn) -> Result<StructColumn, &'static str> {
+        let no_fields = r.fields.is_empty();
+        let mut s = StructColumn::new(r.fields)?;
+        // a struct without fields still has rows, only `len` says how many
+        if no_fields {
+            s.len = r.len;
+        } else if s.len != r.len {
+            return Err("struct length must equal the length of its fields");
+        }
+        Ok(s)
+    }
+}
+
+impl StructColumn {
+    /// fields must have distinct names and equal lengths
+    pub fn new(fields: Vec<(String, Column)>) -> Result<StructColumn, &'static str> {
+        let len = fields.first().map_or(0, |(_, c)| c.len());
+        if fields.iter().any(|(_, c)| c.len() != len) {
+            return Err("struct fields must have equal length");
+        }
+        if fields.iter().enumerate().any(|(i, (n, _))| fields[..i].iter().any(|(m, _)| m == n)) {
+            return Err("struct field names must be distinct");
+        }
+        Ok(StructColumn { len, fields })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Column> {
+        self.fields.iter().find(|(n, _)| n == name).map(|(_, c)| c)
+    }
+
+    /// (name, column) pairs in field order
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Column)> {
+        self.fields.iter().map(|(n, c)| (n.as_str(), c))
+    }
+
+    pub(crate) fn take(&self, idx: &[usize]) -> StructColumn {
+        let fields = self.fields.iter().map(|(n, c)| (n.clone(), c.take(idx))).collect();
+        StructColumn { len: idx.len(), fields }
+    }
+
+    pub(crate) fn slice(&self, offset: usize, len: usize) -> StructColumn {
+        let fields = self.fields.iter().map(|(n, c)| (n.clone(), c.slice(offset, len).to_column())).collect();
+        StructColumn { len, fields }
+    }
+
+    pub(crate) fn concat(structs: Vec<StructColumn>) -> StructColumn {
+        let len = structs.iter().map(|s| s.len).sum();
+        let names: Vec<String> = structs[0].fields.iter().map(|(n, _)| n.clone()).collect();
+        let mut parts: Vec<Vec<Column>> = names.iter().map(|_| Vec::new()).collect();
+        for s in structs {
+            for (p, (_, c)) in parts.iter_mut().zip(s.fields) {
+                p.push(c);
+            }
+        }
+        let fields = names.into_iter().zip(parts).map(|(n, p)| (n, chunked::concat(p, len))).collect();
+        StructColumn { len, fields }
+    }
+}
+
+/// one row per list element, the other columns repeated for each element.
+/// Rows holding an empty list are dropped.
+pub fn explode(df: &DataFrame, col: &str) -> Result<DataFrame, String> {
+    let Some(Column::L(list)) = df.get(col) else {
+        return Err(format!("'{}' is not a list column", col));
+    };
+    let idx: Vec<usize> = list.lengths().into_iter()
+        .enumerate()
+        .flat_map(|(i, n)| std::iter::repeat_n(i, n))
+        .collect();
+    let mut out = DataFrame::new();
+    for (n, c) in df.iter() {
+        let c = if n == col { list.values().clone() } else { c.take(&idx) };
+        out.addcol(n, c).expect("exploded columns have equal length");
+    }
+    Ok(out)
+}
+
+/// inverse of `explode`: one row per group of the `keys` columns, every other
+/// column collected into a list per group
+pub fn implode(df: &DataFrame, keys: &[&str]) -> Result<DataFrame, String> {
+    let groups = groupby::group_rows(df, keys)?;
+    let firsts: Vec<usize> = groups.iter().map(|g| g[0]).collect();
+    let mut offsets = vec![0];
+    offsets.extend(groups.iter().scan(0, |end, g| {
+        *end += g.len();
+        Some(*end)
+    }));
+    let rows: Vec<usize> = groups.into_iter().flatten().collect();
+
+    let mut out = DataFrame::new();
+    for (n, c) in df.iter() {
+        let c = if keys.contains(&n) {
+            c.take(&firsts)
+        } else {
+            Column::L(ListColumn { offsets: offsets.clone(), values: Box::new(c.take(&rows)) })
+        };
+        out.addcol(n, c).expect("one row per group");
+    }
+    Ok(out)
+}
+
+/// replace a struct column with one `<col>.<field>` column per field
+pub fn unnest(df: &DataFrame, col: &str) -> Result<DataFrame, String> {
+    let Some(Column::R(s)) = df.get(col) else {
+        return Err(format!("'{}' is not a struct column", col));
+    };
+    let mut out = DataFrame::new();
+    for (n, c) in df.iter() {
+        if n != col {
+            out.addcol(n, c.clone()).expect("columns of a frame have equal length");
+            continue;
+        }
+        for (f, fc) in s.fields() {
+            let name = format!("{}.{}", col, f);
+            if df.get(&name).is_some() {
+                return Err(format!("unnesting '{}' would replace column '{}'", col, name));
+            }
+            out.addcol(&name, fc.clone()).expect("struct fields have the frame's length");
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+
+    fn tags() -> ListColumn {
+        ListColumn::from_lists(vec![vec![1, 2], vec![], vec![3]])
+    }
+
+    #[test]
+    fn test_list_column() {
+        let l = tags();
+        assert_eq!(l.len(), 3);
+        assert_eq!(l.offsets(), &[0, 2, 2, 3]);
+        assert_eq!(l.lengths(), vec![2, 0, 1]);
+        let ColumnView::I(x) = l.get(0) else { panic!("expected I view") };
+        assert_eq!(x, &[1, 2]);
+
+        let t = l.take(&[2, 0]);
+        assert_eq!(t.offsets(), &[0, 1, 3]);
+        let Column::I(v) = t.values() else { panic!("expected I column") };
+        assert_eq!(v, &vec![3, 1, 2]);
+
+        assert!(ListColumn::new(vec![0, 2], Column::I(vec![1])).is_err());
+        assert!(ListColumn::new(vec![1, 1], Column::I(vec![1])).is_err());
+        assert!(ListColumn::new(vec![0, 2, 1], Column::I(vec![1])).is_err());
+        assert!(ListColumn::new(vec![0, 1], Column::I(vec![1])).is_ok());
+    }
+
+    #[test]
+    fn test_explode_implode() {
+        let df = df! { "id" => ["a", "b", "c"], "tag" => tags() };
+        assert_eq!(df.get("tag").unwrap().type_name(), "list");
+
+        let ex = df.explode("tag").unwrap();
+        assert_eq!(ex.len(), 3);
+        let Some(Column::S(id)) = ex.get("id") else { panic!("expected S column") };
+        assert_eq!(id, &vec!["a", "a", "c"]);
+        assert!(df.explode("id").is_err());
+
+        let im = ex.implode(&["id"]).unwrap();
+        assert_eq!(im.len(), 2);
+        let Some(Column::L(l)) = im.get("tag") else { panic!("expected L column") };
+        assert_eq!(l.lengths(), vec![2, 1]);
+        assert_eq!(im.to_string().lines().nth(2), Some(" a  | [1, 2] "));
+    }
+
+    #[test]
+    fn test_concat_chunks() {
+        let mut c = chunked::ChunkedColumn::from(Column::L(tags()));
+        c.append(Column::L(ListColumn::from_lists(vec![vec![4, 5]]))).unwrap();
+        assert!(c.append(Column::L(ListColumn::from_lists(vec![vec![1.0]]))).is_err());
+        let Some(Column::L(l)) = c.into_column() else { panic!("expected L column") };
+        assert_eq!(l.offsets(), &[0, 2, 2, 3, 5]);
+        let ColumnView::I(x) = l.get(3) else { panic!("expected I view") };
+        assert_eq!(x, &[4, 5]);
+    }
+
+    #[test]
+    fn test_struct_column() {
+        let s = StructColumn::new(vec![
+            ("x".to_string(), Column::I(vec![1, 2])),
+            ("y".to_string(), Column::S(vec!["p".to_string(), "q".to_string()])),
+        ]).unwrap();
+        let df = df! { "id" => [10, 20], "pt" => s };
+        let Some(Column::I(x)) = df.get("pt").unwrap().field("x") else { panic!("expected I column") };
+        assert_eq!(x, &vec![1, 2]);
+        assert_eq!(df.tail(1).to_string().lines().nth(2), Some(" 20 | {x: 2, y: q} "));
+
+        let flat = df.unnest("pt").unwrap();
+        assert_eq!(flat.names().collect::<Vec<_>>(), vec!["id", "pt.x", "pt.y"]);
+        assert!(df.unnest("id").is_err());
+
+        let taken = df.take(&[1]);
+        let Some(Column::S(y)) = taken.get("pt").unwrap().field("y") else { panic!("expected S column") };
+        assert_eq!(y, &vec!["q"]);
+
+        assert!(StructColumn::new(vec![("a".to_string(), Column::I(vec![1])), ("b".to_string(), Column::I(vec![]))]).is_err());
+        assert!(StructColumn::new(vec![("a".to_string(), Column::I(vec![1])), ("a".to_string(), Column::I(vec![2]))]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! The numeric element types, so a kernel written once over `T: Numeric`
+//! serves every integer and float type.
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+
+pub trait Numeric:
+    Copy + PartialOrd + Debug + Send + Sync + 'static
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + AddAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// `v` as this type, saturating at its bounds, NaN gives zero for integers
+    fn from_f64(v: f64) -> Self;
+    /// `v` as this type, saturating at its bounds
+    fn from_usize(v: usize) -> Self;
+    fn to_f64(self) -> f64;
+    /// always false for integers
+    fn is_nan(self) -> bool;
+    /// total order, with NaN after every number
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! int_numeric {
+    ($($t:ty),*) => {
+        $(impl Numeric for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn from_f64(v: f64) -> Self {
+                v as $t
+            }
+
+            fn from_usize(v: usize) -> Self {
+                <$t>::try_from(v).unwrap_or(<$t>::MAX)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn is_nan(self) -> bool {
+                false
+            }
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                self.cmp(other)
+            }
+        })*
+    };
+}
+
+macro_rules! float_numeric {
+    ($($t:ty),*) => {
+        $(impl Numeric for $t {
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+
+            fn from_f64(v: f64) -> Self {
+                v as $t
+            }
+
+            fn from_usize(v: usize) -> Self {
+                v as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn is_nan(self) -> bool {
+                <$t>::is_nan(self)
+            }
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                <$t>::total_cmp(self, other)
+            }
+        })*
+    };
+}
+
+int_numeric!(i32, i64, u32, u64);
+float_numeric!(f32, f64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mid<T: Numeric>(a: T, b: T) -> T {
+        a + (b - a) / (T::ONE + T::ONE)
+    }
+
+    #[test]
+    fn test_generic() {
+        assert_eq!(mid(2u32, 8), 5);
+        assert_eq!(mid(-4i64, 1), -2);
+        assert_eq!(mid(1.0f32, 2.0), 1.5);
+        assert_eq!(i32::ZERO.total_cmp(&i32::ONE), Ordering::Less);
+        assert_eq!(f64::NAN.total_cmp(&f64::INFINITY), Ordering::Greater);
+        assert!(Numeric::is_nan(f32::NAN) && !Numeric::is_nan(3u64));
+    }
+
+    #[test]
+    fn test_casts() {
+        assert_eq!(u32::from_f64(-3.0), 0);
+        assert_eq!(i32::from_f64(1e12), i32::MAX);
+        assert_eq!(i64::from_f64(f64::NAN), 0);
+        assert_eq!(i32::from_usize(usize::MAX), i32::MAX);
+        assert_eq!(u64::from_usize(300), 300);
+        assert_eq!(f32::from_usize(300), 300.0);
+        assert_eq!(u64::MAX.to_f64(), 18446744073709551615.0);
+    }
+}
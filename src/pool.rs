@@ -0,0 +1,304 @@
+//! A work-stealing thread pool for the crate's parallel kernels.
+//!
+//! Each worker keeps its own queue, taking its newest job first and, when
+//! that is empty, stealing the oldest job of another worker. Jobs spawned
+//! from outside the pool go through a shared queue. `ThreadPool::scope`
+//! allows jobs to borrow from the caller like `std::thread::scope`: it only
+//! returns once every job spawned in it has finished, running queued jobs
+//! itself while it waits.
+//!
+//! `compute` and `tree` schedule onto the `global` pool, which is sized to
+//! the available cores unless `init_global` is called before its first use.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // (pool id, worker index) when the current thread is a pool worker
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+struct Shared {
+    id: usize,
+    queues: Vec<Mutex<VecDeque<Job>>>,
+    injector: Mutex<VecDeque<Job>>,
+    // bumped on every push and on shutdown, so a worker going to sleep can
+    // tell whether anything happened since it last looked for work
+    epoch: Mutex<u64>,
+    wake: Condvar,
+    shutdown: AtomicBool,
+}
+
+impl Shared {
+    /// index of the current thread if it is one of this pool's workers
+    fn me(&self) -> Option<usize> {
+        WORKER.with(|w| w.get()).filter(|(id, _)| *id == self.id).map(|(_, i)| i)
+    }
+
+    fn push(&self, job: Job) {
+        match self.me() {
+            Some(i) => self.queues[i].lock().unwrap().push_back(job),
+            None => self.injector.lock().unwrap().push_back(job),
+        }
+        *self.epoch.lock().unwrap() += 1;
+        self.wake.notify_one();
+    }
+
+    fn find(&self, me: Option<usize>) -> Option<Job> {
+        if let Some(job) = me.and_then(|i| self.queues[i].lock().unwrap().pop_back()) {
+            return Some(job);
+        }
+        if let Some(job) = self.injector.lock().unwrap().pop_front() {
+            return Some(job);
+        }
+        let n = self.queues.len();
+        let start = me.map_or(0, |i| i + 1);
+        (0..n).map(|k| (start + k) % n)
+            .filter(|k| Some(*k) != me)
+            .find_map(|k| self.queues[k].lock().unwrap().pop_front())
+    }
+
+    fn run_worker(&self, index: usize) {
+        WORKER.with(|w| w.set(Some((self.id, index))));
+        loop {
+            let seen = *self.epoch.lock().unwrap();
+            if let Some(job) = self.find(Some(index)) {
+                job();
+                continue;
+            }
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            let mut epoch = self.epoch.lock().unwrap();
+            while *epoch == seen && !self.shutdown.load(Ordering::SeqCst) {
+                epoch = self.wake.wait(epoch).unwrap();
+            }
+        }
+    }
+}
+
+/// A fixed set of worker threads running jobs spawned in `scope`s
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// pool of `n_threads` workers (at least one)
+    pub fn new(n_threads: usize) -> ThreadPool {
+        let n = n_threads.max(1);
+        let shared = Arc::new(Shared {
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
+            queues: (0..n).map(|_| Mutex::new(VecDeque::new())).collect(),
+            injector: Mutex::new(VecDeque::new()),
+            epoch: Mutex::new(0),
+            wake: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        let workers = (0..n)
+            .map(|i| {
+                let s = Arc::clone(&shared);
+                thread::Builder::new()
+                    .name(format!("pool-{}-{}", s.id, i))
+                    .spawn(move || s.run_worker(i))
+                    .expect("cannot start pool thread")
+            })
+            .collect();
+        ThreadPool { shared, workers }
+    }
+
+    pub fn n_threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// run `f`, which can spawn jobs borrowing anything that outlives this
+    /// call, and wait for all of them. A panic in `f` or any job is resumed
+    /// here once every job has finished.
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Scope<'env>) -> R,
+    {
+        let scope = Scope {
+            shared: Arc::clone(&self.shared),
+            state: Arc::new(ScopeState::default()),
+            _env: PhantomData,
+        };
+        let out = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.wait();
+        let out = out.unwrap_or_else(|p| panic::resume_unwind(p));
+        if let Some(p) = scope.state.panic.lock().unwrap().take() {
+            panic::resume_unwind(p);
+        }
+        out
+    }
+}
+
+/// sized to the available cores
+impl Default for ThreadPool {
+    fn default() -> ThreadPool {
+        ThreadPool::new(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        *self.shared.epoch.lock().unwrap() += 1;
+        self.shared.wake.notify_all();
+        for w in self.workers.drain(..) {
+            let _ = w.join();
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScopeState {
+    pending: Mutex<usize>,
+    done: Condvar,
+    // the first panic of a job, resumed when the scope ends
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Handle for spawning jobs that may borrow data living for `'env`
+pub struct Scope<'env> {
+    shared: Arc<Shared>,
+    state: Arc<ScopeState>,
+    // invariant, so a scope cannot be passed off as one with a shorter 'env
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> Scope<'env> {
+    /// queue `f` on the pool, it gets the scope to spawn further jobs
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce(&Scope<'env>) + Send + 'env,
+    {
+        *self.state.pending.lock().unwrap() += 1;
+        let scope = Scope { shared: Arc::clone(&self.shared), state: Arc::clone(&self.state), _env: PhantomData };
+        let job: Box<dyn FnOnce() + Send + 'env> = Box::new(move || {
+            let r = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+            if let Err(p) = r {
+                scope.state.panic.lock().unwrap().get_or_insert(p);
+            }
+            let mut pending = scope.state.pending.lock().unwrap();
+            *pending -= 1;
+            if *pending == 0 {
+                scope.state.done.notify_all();
+            }
+        });
+        // safety: `ThreadPool::scope` does not return (or unwind) before the
+        // pending count is back to zero, which happens only after `f` has run
+        // and dropped everything it borrows for 'env
+        let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'env>, Job>(job) };
+        self.shared.push(job);
+    }
+
+    fn wait(&self) {
+        let me = self.shared.me();
+        loop {
+            if *self.state.pending.lock().unwrap() == 0 {
+                return;
+            }
+            // help rather than block, so waiting inside a job cannot starve
+            // the pool of workers
+            if let Some(job) = self.shared.find(me) {
+                job();
+                continue;
+            }
+            let pending = self.state.pending.lock().unwrap();
+            if *pending > 0 {
+                // short timeout as jobs can be queued without notifying us
+                let _ = self.state.done.wait_timeout(pending, Duration::from_millis(1)).unwrap();
+            }
+        }
+    }
+}
+
+static GLOBAL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// size the global pool, only possible before it is first used
+pub fn init_global(n_threads: usize) -> Result<(), String> {
+    GLOBAL.set(ThreadPool::new(n_threads)).map_err(|_| "the global pool is already running".to_string())
+}
+
+/// the pool `compute` and `tree` run on
+pub fn global() -> &'static ThreadPool {
+    GLOBAL.get_or_init(ThreadPool::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scoped_borrow() {
+        let pool = ThreadPool::new(3);
+        assert_eq!(pool.n_threads(), 3);
+        let mut v: Vec<u64> = (0..1000).collect();
+        let total = AtomicUsize::new(0);
+        pool.scope(|s| {
+            for chunk in v.chunks_mut(7) {
+                let total = &total;
+                s.spawn(move |_| {
+                    chunk.iter_mut().for_each(|x| *x *= 2);
+                    total.fetch_add(chunk.len(), Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(total.into_inner(), 1000);
+        assert_eq!(v.iter().sum::<u64>(), 999 * 1000);
+    }
+
+    fn sum<'a>(s: &Scope<'a>, v: &'a [u64], out: &'a AtomicUsize) {
+        if v.len() <= 4 {
+            out.fetch_add(v.iter().sum::<u64>() as usize, Ordering::SeqCst);
+            return;
+        }
+        let (a, b) = v.split_at(v.len() / 2);
+        s.spawn(move |s| sum(s, a, out));
+        s.spawn(move |s| sum(s, b, out));
+    }
+
+    #[test]
+    fn test_nested() {
+        let v: Vec<u64> = (1..=100).collect();
+        let out = AtomicUsize::new(0);
+        // a single worker waiting in an inner scope has to run its jobs itself
+        let pool = ThreadPool::new(1);
+        pool.scope(|s| {
+            s.spawn(|_| pool.scope(|s| sum(s, &v, &out)));
+        });
+        assert_eq!(out.load(Ordering::SeqCst), 5050);
+    }
+
+    #[test]
+    fn test_panic() {
+        let pool = ThreadPool::new(2);
+        let done = AtomicUsize::new(0);
+        let r = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|s| {
+                s.spawn(|_| panic!("boom"));
+                s.spawn(|_| {
+                    done.fetch_add(1, Ordering::SeqCst);
+                });
+            })
+        }));
+        assert!(r.is_err());
+        assert_eq!(done.load(Ordering::SeqCst), 1);
+        // the pool still works afterwards
+        assert_eq!(pool.scope(|_| 7), 7);
+        global();
+        assert!(init_global(2).is_err());
+    }
+}
@@ -0,0 +1,434 @@
+//! Preprocessing transformers that learn their parameters from training data.
+//!
+//! `fit` learns from one frame and `transform` applies what was learned to
+//! any frame holding the same columns, e.g. scaling test data by the training
+//! mean. A `Pipeline` chains transformers, each fitted on the output of the
+//! one before. With the `serde` feature fitted transformers and pipelines
+//! serialize, so a pipeline fitted in training can be replayed at inference.
+//! NaN is a missing value (see `fill`): it is ignored when fitting and stays
+//! NaN through the scalers.
+
+use std::collections::HashMap;
+
+use crate::encode;
+use crate::enum_df::{Column, DataFrame};
+use crate::fill::Fill;
+
+pub trait Transform {
+    /// learn parameters from `df`
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String>;
+    /// apply the learned parameters to `df`, failing if not fitted yet
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String>;
+
+    fn fit_transform(&mut self, df: &DataFrame) -> Result<DataFrame, String> {
+        self.fit(df)?;
+        self.transform(df)
+    }
+}
+
+fn column<'a>(df: &'a DataFrame, n: &str) -> Result<&'a Column, String> {
+    df.get(n).ok_or_else(|| format!("no column named '{}'", n))
+}
+
+/// the learned parameters, one per column of `cols`. Deserialized
+/// transformers are not checked otherwise, so a mismatch is an error here.
+fn fitted<'a, T>(params: &'a Option<Vec<T>>, cols: &[String]) -> Result<&'a [T], String> {
+    let p = params.as_ref().ok_or_else(|| "transformer is not fitted".to_string())?;
+    if p.len() != cols.len() {
+        return Err(format!("transformer has parameters for {} of its {} columns", p.len(), cols.len()));
+    }
+    Ok(p)
+}
+
+/// `f` of the non-NaN values of each of `cols`
+fn stats<T>(df: &DataFrame, cols: &[String], f: impl Fn(&[f64]) -> T) -> Result<Vec<T>, String> {
+    cols.iter().map(|n| {
+        let v: Vec<f64> = encode::values(column(df, n)?)?.into_iter().filter(|x| !x.is_nan()).collect();
+        if v.is_empty() {
+            return Err(format!("column '{}' has no values to fit", n));
+        }
+        Ok(f(&v))
+    }).collect()
+}
+
+/// `df` with the columns in `new` replaced, keeping their position
+fn replace(df: &DataFrame, mut new: HashMap<&str, Column>) -> DataFrame {
+    let mut out = DataFrame::new();
+    for (n, c) in df.iter() {
+        let c = new.remove(n).unwrap_or_else(|| c.clone());
+        out.addcol(n, c).expect("transformed columns keep their length");
+    }
+    out
+}
+
+/// `(x - a) / b` over each of `cols`, as `Column::D`
+fn affine(df: &DataFrame, cols: &[String], params: &[(f64, f64)]) -> Result<DataFrame, String> {
+    let mut new = HashMap::new();
+    for (n, (a, b)) in cols.iter().zip(params) {
+        let v = encode::values(column(df, n)?)?;
+        new.insert(n.as_str(), Column::D(v.into_iter().map(|x| (x - a) / b).collect()));
+    }
+    Ok(replace(df, new))
+}
+
+/// Centres columns on zero mean and scales them to unit standard deviation
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StandardScaler {
+    cols: Vec<String>,
+    // mean and standard deviation of each column
+    params: Option<Vec<(f64, f64)>>,
+}
+
+impl StandardScaler {
+    pub fn new(cols: &[&str]) -> StandardScaler {
+        StandardScaler { cols: cols.iter().map(|c| c.to_string()).collect(), params: None }
+    }
+}
+
+impl Transform for StandardScaler {
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String> {
+        self.params = Some(stats(df, &self.cols, |v| {
+            let n = v.len() as f64;
+            let mean = v.iter().sum::<f64>() / n;
+            let std = (v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+            // a constant column is only centred
+            (mean, if std > 0.0 { std } else { 1.0 })
+        })?);
+        Ok(())
+    }
+
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String> {
+        affine(df, &self.cols, fitted(&self.params, &self.cols)?)
+    }
+}
+
+/// Scales columns so their training range maps onto `[0, 1]`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinMaxScaler {
+    cols: Vec<String>,
+    // minimum and range of each column
+    params: Option<Vec<(f64, f64)>>,
+}
+
+impl MinMaxScaler {
+    pub fn new(cols: &[&str]) -> MinMaxScaler {
+        MinMaxScaler { cols: cols.iter().map(|c| c.to_string()).collect(), params: None }
+    }
+}
+
+impl Transform for MinMaxScaler {
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String> {
+        self.params = Some(stats(df, &self.cols, |v| {
+            let min = v.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = v.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            (min, if max > min { max - min } else { 1.0 })
+        })?);
+        Ok(())
+    }
+
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String> {
+        affine(df, &self.cols, fitted(&self.params, &self.cols)?)
+    }
+}
+
+/// Replaces string columns by the index of each value among the sorted
+/// training values, -1 for values not seen in training
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelEncoder {
+    cols: Vec<String>,
+    classes: Option<Vec<Vec<String>>>,
+}
+
+impl LabelEncoder {
+    pub fn new(cols: &[&str]) -> LabelEncoder {
+        LabelEncoder { cols: cols.iter().map(|c| c.to_string()).collect(), classes: None }
+    }
+
+    /// the values learned for `col`, in code order
+    pub fn classes(&self, col: &str) -> Option<&[String]> {
+        let i = self.cols.iter().position(|c| c == col)?;
+        self.classes.as_ref()?.get(i).map(Vec::as_slice)
+    }
+}
+
+fn strings<'a>(df: &'a DataFrame, n: &str) -> Result<&'a [String], String> {
+    match column(df, n)? {
+        Column::S(x) => Ok(x),
+        _ => Err(format!("'{}' is not a string column", n)),
+    }
+}
+
+impl Transform for LabelEncoder {
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String> {
+        let classes = self.cols.iter().map(|n| {
+            let mut v = strings(df, n)?.to_vec();
+            v.sort();
+            v.dedup();
+            Ok(v)
+        }).collect::<Result<_, String>>()?;
+        self.classes = Some(classes);
+        Ok(())
+    }
+
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String> {
+        let mut new = HashMap::new();
+        for (n, classes) in self.cols.iter().zip(fitted(&self.classes, &self.cols)?) {
+            // codes come from a binary search
+            if !classes.windows(2).all(|w| w[0] < w[1]) {
+                return Err(format!("classes of '{}' are not sorted and distinct", n));
+            }
+            let codes = strings(df, n)?.iter()
+                .map(|s| classes.binary_search(s).map_or(-1, |i| i as i32))
+                .collect();
+            new.insert(n.as_str(), Column::I(codes));
+        }
+        Ok(replace(df, new))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Impute {
+    Mean,
+    Median,
+    Constant(f64),
+}
+
+/// Fills missing values with a per column value learned in training. Like
+/// `fill`, integer columns are left as they are.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Imputer {
+    cols: Vec<String>,
+    strategy: Impute,
+    values: Option<Vec<f64>>,
+}
+
+impl Imputer {
+    pub fn new(cols: &[&str], strategy: Impute) -> Imputer {
+        Imputer { cols: cols.iter().map(|c| c.to_string()).collect(), strategy, values: None }
+    }
+}
+
+impl Transform for Imputer {
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String> {
+        // a constant needs no values, so also fills all-NaN columns
+        if let Impute::Constant(c) = self.strategy {
+            for n in &self.cols {
+                column(df, n)?;
+            }
+            self.values = Some(vec![c; self.cols.len()]);
+            return Ok(());
+        }
+        self.values = Some(stats(df, &self.cols, |v| match self.strategy {
+            Impute::Mean => v.iter().sum::<f64>() / v.len() as f64,
+            Impute::Median => {
+                let mut v = v.to_vec();
+                v.sort_by(f64::total_cmp);
+                let m = v.len() / 2;
+                if v.len().is_multiple_of(2) { (v[m - 1] + v[m]) / 2.0 } else { v[m] }
+            }
+            Impute::Constant(c) => c,
+        })?);
+        Ok(())
+    }
+
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String> {
+        let mut new = HashMap::new();
+        for (n, v) in self.cols.iter().zip(fitted(&self.values, &self.cols)?) {
+            new.insert(n.as_str(), column(df, n)?.fill(Fill::Value(*v)));
+        }
+        Ok(replace(df, new))
+    }
+}
+
+/// Any one of the transformers, as a step of a `Pipeline`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transformer {
+    StandardScaler(StandardScaler),
+    MinMaxScaler(MinMaxScaler),
+    LabelEncoder(LabelEncoder),
+    Imputer(Imputer),
+}
+
+macro_rules! dispatch {
+    ($self:ident, $t:ident => $e:expr) => {
+        match $self {
+            Transformer::StandardScaler($t) => $e,
+            Transformer::MinMaxScaler($t) => $e,
+            Transformer::LabelEncoder($t) => $e,
+            Transformer::Imputer($t) => $e,
+        }
+    };
+}
+
+impl Transform for Transformer {
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String> {
+        dispatch!(self, t => t.fit(df))
+    }
+
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String> {
+        dispatch!(self, t => t.transform(df))
+    }
+}
+
+macro_rules! into_transformer {
+    ($($t:ident),*) => {
+        $(impl From<$t> for Transformer {
+            fn from(t: $t) -> Transformer {
+                Transformer::$t(t)
+            }
+        })*
+    };
+}
+
+into_transformer!(StandardScaler, MinMaxScaler, LabelEncoder, Imputer);
+
+/// Transformers applied in order
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pipeline {
+    steps: Vec<Transformer>,
+}
+
+impl Pipeline {
+    pub fn new(steps: Vec<Transformer>) -> Pipeline {
+        Pipeline { steps }
+    }
+
+    pub fn push(&mut self, step: impl Into<Transformer>) {
+        self.steps.push(step.into());
+    }
+
+    pub fn steps(&self) -> &[Transformer] {
+        &self.steps
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Pipeline {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(s: &str) -> Result<Pipeline, String> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+}
+
+impl Transform for Pipeline {
+    fn fit(&mut self, df: &DataFrame) -> Result<(), String> {
+        self.fit_transform(df).map(|_| ())
+    }
+
+    fn transform(&self, df: &DataFrame) -> Result<DataFrame, String> {
+        let mut out: Option<DataFrame> = None;
+        for s in &self.steps {
+            out = Some(s.transform(out.as_ref().unwrap_or(df))?);
+        }
+        Ok(out.unwrap_or_else(|| df.clone()))
+    }
+
+    fn fit_transform(&mut self, df: &DataFrame) -> Result<DataFrame, String> {
+        let mut out: Option<DataFrame> = None;
+        for s in &mut self.steps {
+            out = Some(s.fit_transform(out.as_ref().unwrap_or(df))?);
+        }
+        Ok(out.unwrap_or_else(|| df.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compare::CompareOptions;
+    use crate::df;
+
+    fn doubles(df: &DataFrame, n: &str) -> Vec<f64> {
+        let Some(Column::D(x)) = df.get(n) else { panic!("expected D column") };
+        x.clone()
+    }
+
+    #[test]
+    fn test_scalers() {
+        let train = df! { "x" => [1.0, 3.0, f64::NAN, 5.0], "n" => [0, 10, 0, 10], "k" => ["a", "b", "c", "d"] };
+        let mut s = StandardScaler::new(&["x", "n"]);
+        assert!(s.transform(&train).is_err());
+        let out = s.fit_transform(&train).unwrap();
+        let x = doubles(&out, "x");
+        assert!((x[0] + 1.224744).abs() < 1e-5 && x[1] == 0.0 && x[2].is_nan());
+        assert_eq!(doubles(&out, "n"), vec![-1.0, 1.0, -1.0, 1.0]);
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["x", "n", "k"]);
+
+        let mut m = MinMaxScaler::new(&["x"]);
+        m.fit(&train).unwrap();
+        let out = m.transform(&df! { "x" => [3.0, 9.0] }).unwrap();
+        assert_eq!(doubles(&out, "x"), vec![0.5, 2.0]);
+        assert!(MinMaxScaler::new(&["k"]).fit(&train).is_err());
+        assert!(MinMaxScaler::new(&["missing"]).fit(&train).is_err());
+    }
+
+    #[test]
+    fn test_encoder_imputer() {
+        let train = df! { "c" => ["red", "blue", "red"], "v" => [1.0, f64::NAN, 4.0] };
+        let mut e = LabelEncoder::new(&["c"]);
+        e.fit(&train).unwrap();
+        assert_eq!(e.classes("c").unwrap(), ["blue", "red"]);
+        let out = e.transform(&df! { "c" => ["red", "green"] }).unwrap();
+        assert_eq!(out, df! { "c" => [1, -1] });
+        assert!(LabelEncoder::new(&["v"]).fit(&train).is_err());
+
+        let mut i = Imputer::new(&["v"], Impute::Mean);
+        i.fit(&train).unwrap();
+        let out = i.transform(&df! { "v" => [f64::NAN, 7.0] }).unwrap();
+        assert_eq!(doubles(&out, "v"), vec![2.5, 7.0]);
+        let mut i = Imputer::new(&["v"], Impute::Constant(-1.0));
+        assert_eq!(doubles(&i.fit_transform(&train).unwrap(), "v"), vec![1.0, -1.0, 4.0]);
+        let empty = df! { "v" => [f64::NAN, f64::NAN] };
+        assert_eq!(doubles(&i.fit_transform(&empty).unwrap(), "v"), vec![-1.0, -1.0]);
+        assert!(Imputer::new(&["v"], Impute::Mean).fit(&empty).is_err());
+        assert!(i.fit(&df! { "w" => [1.0] }).is_err());
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let train = df! { "x" => [0.0, f64::NAN, 10.0], "c" => ["b", "a", "b"] };
+        let mut p = Pipeline::new(vec![Imputer::new(&["x"], Impute::Median).into()]);
+        p.push(MinMaxScaler::new(&["x"]));
+        p.push(LabelEncoder::new(&["c"]));
+        assert!(p.transform(&train).is_err());
+        let out = p.fit_transform(&train).unwrap();
+        assert_eq!(out, df! { "x" => [0.0, 0.5, 1.0], "c" => [1, 0, 1] });
+        let out = p.transform(&df! { "x" => [f64::NAN, 20.0], "c" => ["a", "z"] }).unwrap();
+        assert_eq!(out, df! { "x" => [0.5, 2.0], "c" => [0, -1] });
+        let same = Pipeline::default().transform(&train).unwrap();
+        assert!(same.approx_eq(&train, &CompareOptions::exact()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_pipeline_json() {
+        let train = df! { "x" => [1.0, 2.0, 3.0], "c" => ["b", "a", "b"] };
+        let mut p = Pipeline::new(vec![StandardScaler::new(&["x"]).into(), LabelEncoder::new(&["c"]).into()]);
+        p.fit(&train).unwrap();
+        let json = p.to_json().unwrap();
+        let q = Pipeline::from_json(&json).unwrap();
+        assert_eq!(q, p);
+        let test = df! { "x" => [2.0, 5.0], "c" => ["a", "q"] };
+        assert_eq!(q.transform(&test).unwrap(), p.transform(&test).unwrap());
+        assert!(Pipeline::from_json("{").is_err());
+
+        // parameters that do not match the columns are caught on use
+        let e: LabelEncoder = serde_json::from_str(r#"{"cols":["c","d"],"classes":[["a","b"]]}"#).unwrap();
+        assert_eq!(e.classes("d"), None);
+        assert!(e.transform(&df! { "c" => ["a"], "d" => ["b"] }).is_err());
+        let e: LabelEncoder = serde_json::from_str(r#"{"cols":["c"],"classes":[["b","a"]]}"#).unwrap();
+        assert!(e.transform(&df! { "c" => ["a"] }).is_err());
+        let s: StandardScaler = serde_json::from_str(r#"{"cols":["x"],"params":[]}"#).unwrap();
+        assert!(s.transform(&test).is_err());
+    }
+}
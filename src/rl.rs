@@ -13,11 +13,12 @@ impl CardDeck {
         CardDeck{rng: thread_rng(), cards: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 10, 10, 10]}
     }
     fn draw(&mut self) -> u8 {
-        return *self.cards.choose(&mut self.rng).unwrap();
+        *self.cards.choose(&mut self.rng).unwrap()
     }
 }
 
 #[derive(Copy, Clone, Eq, Hash, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlackJackState {
     pub dealer_shown: u8,
     pub player_sum: u8,
@@ -30,6 +31,7 @@ pub struct BlackJackEnv {
     dealer_ace: bool,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transition {
     pub next_state: BlackJackState,
     pub reward: i8,
@@ -45,11 +47,11 @@ impl BlackJackEnv {
         };
         env.deal_dealer();
         env.deal_player();
-        return env;
+        env
     }
 
     pub fn get_state(&self) -> BlackJackState {
-        return self.state;
+        self.state
     }
 
     fn check_ace(card: u8, sum: u8, ace: bool) -> bool {
@@ -59,7 +61,7 @@ impl BlackJackEnv {
         else if card == 1 {
             return true;
         }
-        return ace;
+        ace
     }
 
     fn deal_dealer(&mut self) {
@@ -114,7 +116,7 @@ impl BlackJackEnv {
             reward = self.final_result();
             terminated = true;
         }
-        return Transition {
+        Transition {
             next_state: self.state,
             reward,
             terminated,
@@ -123,8 +125,19 @@ impl BlackJackEnv {
 
 }
 
+impl Default for BlackJackEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 
-struct BlackJackAgent {
+/// Tabular Q-learning agent. With the `serde` feature the agent, including its
+/// learned Q-table, can be saved and restored.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlackJackAgent {
+    // stored as a list of pairs since formats like json need string map keys
+    #[cfg_attr(feature = "serde", serde(with = "q_table_pairs"))]
     q_table: HashMap<BlackJackState, [f32; 2]>,
     epsilon: f32,
     epsilon_decay: f32,
@@ -134,7 +147,7 @@ struct BlackJackAgent {
 }
 
 impl BlackJackAgent {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             q_table: HashMap::new(),
             epsilon: 1.0,
@@ -156,11 +169,11 @@ impl BlackJackAgent {
                 return true;
             }
         }
-        return false;
+        false
     }
 
     fn update(&mut self, hit: bool, start: &BlackJackState, tx: &Transition) {
-        let current_q = match self.q_table.get(&start) {
+        let current_q = match self.q_table.get(start) {
             Some(q) => q[hit as usize],
             None => 0.0,
         };
@@ -179,8 +192,8 @@ impl BlackJackAgent {
         };
         let newq = current_q +
             self.lr * (tx.reward as f32 + self.discount * future_q - current_q);
-        if self.q_table.contains_key(&start) {
-            self.q_table.get_mut(&start).unwrap()[hit as usize] = newq;
+        if self.q_table.contains_key(start) {
+            self.q_table.get_mut(start).unwrap()[hit as usize] = newq;
         }
         else {
             let mut newarr = [0.0, 0.0];
@@ -197,7 +210,7 @@ impl BlackJackAgent {
         };
     }
 
-    fn play(&mut self, n_games: i32, train: bool) -> i32 {
+    pub fn play(&mut self, n_games: i32, train: bool) -> i32 {
         let mut total_reward = 0;
         for _ in 0..n_games {
             let mut env = BlackJackEnv::new();
@@ -217,7 +230,35 @@ impl BlackJackAgent {
                 self.decay_epsilon();
             }
         }
-        return total_reward;
+        total_reward
+    }
+}
+
+impl Default for BlackJackAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod q_table_pairs {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::BlackJackState;
+
+    pub fn serialize<S: Serializer>(
+        q: &HashMap<BlackJackState, [f32; 2]>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.collect_seq(q.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<HashMap<BlackJackState, [f32; 2]>, D::Error> {
+        Ok(Vec::<(BlackJackState, [f32; 2])>::deserialize(d)?.into_iter().collect())
     }
 }
 
@@ -271,6 +312,27 @@ mod test {
         assert!(pretrain_score < posttrain_score);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_agent_serde() {
+        let mut agent = BlackJackAgent::new();
+        agent.play(100, true);
+        let s = serde_json::to_string(&agent).unwrap();
+        let restored: BlackJackAgent = serde_json::from_str(&s).unwrap();
+        assert_eq!(restored.q_table, agent.q_table);
+        assert_eq!(restored.epsilon, agent.epsilon);
+
+        let tx = Transition {
+            next_state: BlackJackState { dealer_shown: 4, player_sum: 12, usable_ace: true },
+            reward: -1,
+            terminated: true,
+        };
+        let s = serde_json::to_string(&tx).unwrap();
+        let back: Transition = serde_json::from_str(&s).unwrap();
+        assert_eq!(back.next_state, tx.next_state);
+        assert_eq!(back.reward, -1);
+    }
+
     #[test]
     fn test_struct_update() {
         let agent = BlackJackAgent {
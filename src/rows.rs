@@ -0,0 +1,205 @@
+//! Conversion between user structs and `enum_df` rows.
+//!
+//! `FromRow` and `IntoRows` are normally derived, each named field maps to the
+//! column of the same name:
+//!
+//! ```
+//! use rusttest::enum_df::DataFrame;
+//! use rusttest::rows::{FromRow, IntoRows};
+//!
+//! #[derive(FromRow, IntoRows, Debug, PartialEq)]
+//! struct Trade {
+//!     symbol: String,
+//!     qty: i32,
+//!     price: f64,
+//! }
+//!
+//! let trades = vec![
+//!     Trade { symbol: "A".to_string(), qty: 10, price: 1.5 },
+//!     Trade { symbol: "B".to_string(), qty: 5, price: 2.0 },
+//! ];
+//! let df = DataFrame::from_rows(trades);
+//! let back: Vec<Trade> = df.rows::<Trade>().collect::<Result<_, _>>().unwrap();
+//! assert_eq!(back[1].qty, 5);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::enum_df::{Column, DataFrame};
+
+pub use rusttest_derive::{FromRow, IntoRows};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowError {
+    MissingColumn(String),
+    TypeMismatch {
+        column: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowError::MissingColumn(c) => write!(f, "no column named '{}'", c),
+            RowError::TypeMismatch { column, expected, found } => write!(
+                f, "column '{}' holds {} values, cannot read as {}", column, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RowError {}
+
+/// Element types that can be stored in an `enum_df::Column`
+pub trait ColumnType: Sized + Clone + 'static {
+    const NAME: &'static str;
+
+    /// the column's data, or None if the column holds a different type
+    fn slice(col: &Column) -> Option<&[Self]>;
+    fn slice_mut(col: &mut Column) -> Option<&mut [Self]>;
+    fn into_column(v: Vec<Self>) -> Column;
+
+    /// value at `row`, or None if the column holds a different type
+    fn get(col: &Column, row: usize) -> Option<Self> {
+        Self::slice(col).map(|x| x[row].clone())
+    }
+}
+
+macro_rules! column_type {
+    ($t:ty, $variant:ident) => {
+        impl ColumnType for $t {
+            const NAME: &'static str = stringify!($t);
+
+            fn slice(col: &Column) -> Option<&[Self]> {
+                match col {
+                    Column::$variant(x) => Some(x),
+                    _ => None,
+                }
+            }
+
+            fn slice_mut(col: &mut Column) -> Option<&mut [Self]> {
+                match col {
+                    Column::$variant(x) => Some(x),
+                    _ => None,
+                }
+            }
+
+            fn into_column(v: Vec<Self>) -> Column {
+                Column::$variant(v)
+            }
+        }
+    };
+}
+
+column_type!(f32, F);
+column_type!(f64, D);
+column_type!(i32, I);
+column_type!(String, S);
+
+/// Build a value from one row of a dataframe
+pub trait FromRow: Sized {
+    fn from_row(df: &DataFrame, row: usize) -> Result<Self, RowError>;
+}
+
+/// Build a dataframe with one column per field from a set of values
+pub trait IntoRows: Sized {
+    fn into_frame(rows: Vec<Self>) -> DataFrame;
+}
+
+/// read a single typed field out of a row, used by the `FromRow` derive
+pub fn get_field<T: ColumnType>(df: &DataFrame, name: &str, row: usize) -> Result<T, RowError> {
+    let col = df.get(name).ok_or_else(|| RowError::MissingColumn(name.to_string()))?;
+    T::get(col, row).ok_or_else(|| RowError::TypeMismatch {
+        column: name.to_string(),
+        expected: T::NAME,
+        found: col.type_name(),
+    })
+}
+
+/// Iterator returned by `DataFrame::rows`
+pub struct Rows<'a, T> {
+    df: &'a DataFrame,
+    row: usize,
+    _t: PhantomData<T>,
+}
+
+impl<'a, T> Rows<'a, T> {
+    pub(crate) fn new(df: &'a DataFrame) -> Self {
+        Rows { df, row: 0, _t: PhantomData }
+    }
+}
+
+impl<T: FromRow> Iterator for Rows<'_, T> {
+    type Item = Result<T, RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row >= self.df.len() {
+            return None;
+        }
+        self.row += 1;
+        Some(T::from_row(self.df, self.row - 1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(FromRow, IntoRows, Debug, PartialEq)]
+    struct Trade {
+        symbol: String,
+        qty: i32,
+        price: f64,
+        fee: f32,
+    }
+
+    fn trades() -> Vec<Trade> {
+        vec![
+            Trade { symbol: "A".to_string(), qty: 10, price: 1.5, fee: 0.1 },
+            Trade { symbol: "B".to_string(), qty: 5, price: 2.0, fee: 0.2 },
+            Trade { symbol: "C".to_string(), qty: 1, price: 9.0, fee: 0.0 },
+        ]
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let df = DataFrame::from_rows(trades());
+        assert_eq!(df.len(), 3);
+        let Some(Column::I(q)) = df.get("qty") else { panic!("expected I column") };
+        assert_eq!(q, &vec![10, 5, 1]);
+        assert!(matches!(df.get("fee"), Some(Column::F(_))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let df = DataFrame::from_rows(trades());
+        let back = df.rows::<Trade>().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(back, trades());
+    }
+
+    #[derive(FromRow, Debug)]
+    #[allow(dead_code)]
+    struct WrongType {
+        qty: f64,
+    }
+
+    #[derive(FromRow, Debug)]
+    #[allow(dead_code)]
+    struct WrongName {
+        quantity: i32,
+    }
+
+    #[test]
+    fn test_row_errors() {
+        let df = DataFrame::from_rows(trades());
+        let err = df.rows::<WrongType>().next().unwrap().unwrap_err();
+        assert_eq!(err, RowError::TypeMismatch { column: "qty".to_string(), expected: "f64", found: "i32" });
+        assert_eq!(err.to_string(), "column 'qty' holds i32 values, cannot read as f64");
+
+        let err = df.rows::<WrongName>().next().unwrap().unwrap_err();
+        assert_eq!(err, RowError::MissingColumn("quantity".to_string()));
+    }
+}
@@ -0,0 +1,135 @@
+//! Random sampling and shuffling of `enum_df` rows.
+//!
+//! Every function takes an optional seed, the same seed always picks the same
+//! rows. Without one the generator is seeded from the OS.
+
+use rand::rngs::StdRng;
+use rand::seq::{index, SliceRandom};
+use rand::{Rng, SeedableRng};
+
+use crate::enum_df::DataFrame;
+use crate::groupby;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleSize {
+    /// a fixed number of rows
+    N(usize),
+    /// a fraction of the rows, rounded to the nearest row
+    Frac(f64),
+}
+
+impl SampleSize {
+    pub(crate) fn rows(&self, len: usize) -> Result<usize, String> {
+        match *self {
+            SampleSize::N(n) => Ok(n),
+            SampleSize::Frac(f) if f.is_finite() && f >= 0.0 => Ok((f * len as f64).round() as usize),
+            SampleSize::Frac(f) => Err(format!("invalid sample fraction {}", f)),
+        }
+    }
+}
+
+pub(crate) fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// `n` positions out of `0..len`
+fn pick(rng: &mut StdRng, len: usize, n: usize, replace: bool) -> Result<Vec<usize>, String> {
+    if replace {
+        if len == 0 && n > 0 {
+            return Err("cannot sample from no rows".to_string());
+        }
+        return Ok((0..n).map(|_| rng.gen_range(0..len)).collect());
+    }
+    if n > len {
+        return Err(format!("cannot take {} rows from {} without replacement", n, len));
+    }
+    Ok(index::sample(rng, len, n).into_vec())
+}
+
+/// random rows of `df`
+pub fn sample(df: &DataFrame, size: SampleSize, replace: bool, seed: Option<u64>) -> Result<DataFrame, String> {
+    let n = size.rows(df.len())?;
+    let idx = pick(&mut rng(seed), df.len(), n, replace)?;
+    Ok(df.take(&idx))
+}
+
+/// all rows of `df` in random order
+pub fn shuffle(df: &DataFrame, seed: Option<u64>) -> DataFrame {
+    let mut idx: Vec<usize> = (0..df.len()).collect();
+    idx.shuffle(&mut rng(seed));
+    df.take(&idx)
+}
+
+/// sample each group of the `key` column separately, so every group is
+/// represented. `size` applies per group, a fraction keeps group proportions.
+pub fn sample_by(
+    df: &DataFrame, key: &str, size: SampleSize, replace: bool, seed: Option<u64>,
+) -> Result<DataFrame, String> {
+    let mut rng = rng(seed);
+    let mut idx = Vec::new();
+    for rows in groupby::group_rows(df, &[key])? {
+        let n = size.rows(rows.len())?;
+        idx.extend(pick(&mut rng, rows.len(), n, replace)?.into_iter().map(|i| rows[i]));
+    }
+    Ok(df.take(&idx))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+    use crate::enum_df::Column;
+
+    fn ints(df: &DataFrame, n: &str) -> Vec<i32> {
+        let Some(Column::I(x)) = df.get(n) else { panic!("expected I column") };
+        x.clone()
+    }
+
+    #[test]
+    fn test_sample() {
+        let df = df! { "a" => Column::irange(100) };
+        let s = sample(&df, SampleSize::N(10), false, Some(7)).unwrap();
+        assert_eq!(s.len(), 10);
+        assert_eq!(ints(&s, "a"), ints(&sample(&df, SampleSize::N(10), false, Some(7)).unwrap(), "a"));
+        let mut v = ints(&s, "a");
+        v.sort();
+        v.dedup();
+        assert_eq!(v.len(), 10);
+
+        assert_eq!(sample(&df, SampleSize::Frac(0.25), false, None).unwrap().len(), 25);
+        assert_eq!(sample(&df, SampleSize::N(500), true, Some(1)).unwrap().len(), 500);
+        assert!(sample(&df, SampleSize::N(101), false, None).is_err());
+        assert!(sample(&df, SampleSize::Frac(-1.0), false, None).is_err());
+        assert!(sample(&DataFrame::new(), SampleSize::N(1), true, None).is_err());
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let df = df! { "a" => Column::irange(50), "b" => Column::irange(50) };
+        let s = shuffle(&df, Some(3));
+        assert_eq!(ints(&s, "a"), ints(&s, "b"));
+        assert_ne!(ints(&s, "a"), ints(&df, "a"));
+        let mut v = ints(&s, "a");
+        v.sort();
+        assert_eq!(v, ints(&df, "a"));
+        assert_eq!(ints(&shuffle(&df, Some(3)), "a"), ints(&s, "a"));
+    }
+
+    #[test]
+    fn test_sample_by() {
+        let k: Vec<i32> = (0..100).map(|i| if i < 80 { 0 } else { 1 }).collect();
+        let df = df! { "k" => k, "v" => Column::irange(100) };
+        let s = sample_by(&df, "k", SampleSize::Frac(0.5), false, Some(11)).unwrap();
+        let k = ints(&s, "k");
+        assert_eq!(k.iter().filter(|x| **x == 0).count(), 40);
+        assert_eq!(k.iter().filter(|x| **x == 1).count(), 10);
+
+        let s = sample_by(&df, "k", SampleSize::N(5), false, Some(11)).unwrap();
+        assert_eq!(s.len(), 10);
+        assert!(sample_by(&df, "k", SampleSize::N(30), false, None).is_err());
+        assert!(sample_by(&df, "missing", SampleSize::N(1), false, None).is_err());
+    }
+}
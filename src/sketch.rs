@@ -0,0 +1,414 @@
+//! Approximate summaries of large columns in bounded memory.
+//!
+//! `HyperLogLog` estimates distinct counts, `TDigest` estimates quantiles and
+//! `CountMin` estimates value frequencies and tracks the most frequent values.
+//! Each implements `Sketch`: states built over separate chunks of a column can
+//! be merged into the state of the whole column, see `compute::sketch_chunks`.
+//! NaN marks a missing value (see `fill`) and is skipped by every sketch.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+
+use crate::enum_df::ColumnView;
+
+pub trait Sketch {
+    /// add every value of a column
+    fn update(&mut self, c: ColumnView) -> Result<(), String>;
+    /// fold in the state of another sketch, as if its values had been added
+    fn merge(&mut self, other: &Self) -> Result<(), String>;
+}
+
+fn is_missing(c: &ColumnView, i: usize) -> bool {
+    match c {
+        ColumnView::F(x) => x[i].is_nan(),
+        ColumnView::D(x) => x[i].is_nan(),
+        _ => false,
+    }
+}
+
+fn hash_at(c: &ColumnView, i: usize) -> u64 {
+    let mut h = DefaultHasher::new();
+    match c {
+        ColumnView::F(x) => x[i].to_bits().hash(&mut h),
+        ColumnView::D(x) => x[i].to_bits().hash(&mut h),
+        ColumnView::I(x) => x[i].hash(&mut h),
+        ColumnView::S(x) => x[i].hash(&mut h),
+        _ => c.fmt_value(i).hash(&mut h),
+    }
+    h.finish()
+}
+
+fn hash_str(s: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    s.hash(&mut h);
+    h.finish()
+}
+
+/// Distinct count estimate with a relative error of about `1.04 / sqrt(2^p)`
+#[derive(Clone, Debug)]
+pub struct HyperLogLog {
+    p: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// a sketch with `2^p` registers, `p` in 4..=18
+    pub fn new(p: u32) -> Result<HyperLogLog, &'static str> {
+        if !(4..=18).contains(&p) {
+            return Err("precision must be between 4 and 18");
+        }
+        Ok(HyperLogLog { p, registers: vec![0; 1 << p] })
+    }
+
+    fn add_hash(&mut self, h: u64) {
+        let idx = (h >> (64 - self.p)) as usize;
+        let rank = ((h << self.p) | (1 << (self.p - 1))).leading_zeros() as u8 + 1;
+        self.registers[idx] = self.registers[idx].max(rank);
+    }
+
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        // linear counting is more accurate while many registers are unset
+        if raw <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { raw }
+    }
+}
+
+impl Sketch for HyperLogLog {
+    fn update(&mut self, c: ColumnView) -> Result<(), String> {
+        for i in (0..c.len()).filter(|i| !is_missing(&c, *i)) {
+            self.add_hash(hash_at(&c, i));
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, other: &Self) -> Result<(), String> {
+        if self.p != other.p {
+            return Err(format!("cannot merge sketches of precision {} and {}", self.p, other.p));
+        }
+        for (r, o) in self.registers.iter_mut().zip(&other.registers) {
+            *r = (*r).max(*o);
+        }
+        Ok(())
+    }
+}
+
+/// Quantile estimate, most accurate near the tails. Values are kept as
+/// weighted centroids, their number bounded by about `compression`.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    compression: f64,
+    // (mean, weight) sorted by mean
+    centroids: Vec<(f64, f64)>,
+    // values and merged-in centroids not yet compressed
+    buffer: Vec<(f64, f64)>,
+    count: f64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> TDigest {
+        TDigest {
+            compression: compression.max(10.0),
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// number of values added
+    pub fn count(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn add(&mut self, x: f64) {
+        if !x.is_nan() {
+            self.push(x, 1.0);
+        }
+    }
+
+    fn push(&mut self, mean: f64, weight: f64) {
+        self.buffer.push((mean, weight));
+        self.count += weight;
+        self.min = self.min.min(mean);
+        self.max = self.max.max(mean);
+        if self.buffer.len() as f64 >= 5.0 * self.compression {
+            self.centroids = self.merged();
+            self.buffer.clear();
+        }
+    }
+
+    /// largest cumulative fraction a centroid starting at fraction `q` may
+    /// reach, from the arcsine scale function
+    fn q_limit(&self, q: f64) -> f64 {
+        let d = self.compression;
+        let k = d / (2.0 * PI) * (2.0 * q - 1.0).asin() + 1.0;
+        if k >= d / 4.0 { 1.0 } else { ((k * 2.0 * PI / d).sin() + 1.0) / 2.0 }
+    }
+
+    /// the centroids with the buffer merged in
+    fn merged(&self) -> Vec<(f64, f64)> {
+        if self.buffer.is_empty() {
+            return self.centroids.clone();
+        }
+        let mut all: Vec<(f64, f64)> = self.centroids.iter().chain(&self.buffer).copied().collect();
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut out = Vec::new();
+        let mut cur = all[0];
+        let mut before = 0.0;
+        let mut limit = self.count * self.q_limit(0.0);
+        for &(m, w) in &all[1..] {
+            if before + cur.1 + w <= limit {
+                cur.1 += w;
+                cur.0 += (m - cur.0) * w / cur.1;
+            } else {
+                before += cur.1;
+                out.push(cur);
+                limit = self.count * self.q_limit(before / self.count);
+                cur = (m, w);
+            }
+        }
+        out.push(cur);
+        out
+    }
+
+    /// estimated `q` quantile, NaN if no values were added
+    pub fn quantile(&self, q: f64) -> f64 {
+        let c = self.merged();
+        if c.is_empty() {
+            return f64::NAN;
+        }
+        let target = q.clamp(0.0, 1.0) * self.count;
+        // each centroid's weight is centred on its mean, the min and max sit
+        // at the ends
+        let mut at = (0.0, self.min);
+        let mut cum = 0.0;
+        for &(m, w) in &c {
+            let centre = cum + w / 2.0;
+            if target < centre {
+                return lerp(at, (centre, m), target);
+            }
+            at = (centre, m);
+            cum += w;
+        }
+        lerp(at, (self.count, self.max), target)
+    }
+}
+
+fn lerp((x0, y0): (f64, f64), (x1, y1): (f64, f64), x: f64) -> f64 {
+    if x1 <= x0 { y1 } else { y0 + (y1 - y0) * (x - x0) / (x1 - x0) }
+}
+
+impl Sketch for TDigest {
+    fn update(&mut self, c: ColumnView) -> Result<(), String> {
+        match c {
+            ColumnView::F(x) => x.iter().for_each(|v| self.add(*v as f64)),
+            ColumnView::D(x) => x.iter().for_each(|v| self.add(*v)),
+            ColumnView::I(x) => x.iter().for_each(|v| self.add(*v as f64)),
+            _ => return Err("quantiles need a numeric column".to_string()),
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, other: &Self) -> Result<(), String> {
+        for (m, w) in other.merged() {
+            self.push(m, w);
+        }
+        // the centroid means lie inside the other digest's range, not on it
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        Ok(())
+    }
+}
+
+/// Frequency estimate that never undercounts, overcounting by at most
+/// `e / width` of all values with probability `1 - exp(-depth)`. Also keeps
+/// the `top` values with the highest estimates seen.
+#[derive(Clone, Debug)]
+pub struct CountMin {
+    width: usize,
+    depth: usize,
+    counts: Vec<u64>,
+    total: u64,
+    top: usize,
+    heavy: HashMap<String, u64>,
+}
+
+impl CountMin {
+    pub fn new(width: usize, depth: usize, top: usize) -> Result<CountMin, &'static str> {
+        if width == 0 || depth == 0 {
+            return Err("width and depth must be positive");
+        }
+        Ok(CountMin { width, depth, counts: vec![0; width * depth], total: 0, top, heavy: HashMap::new() })
+    }
+
+    /// the counter of each row for a value's hash, from two halves of the hash
+    fn cells(&self, h: u64) -> impl Iterator<Item = usize> {
+        let (a, b, w) = (h as u32 as usize, (h >> 32) as usize, self.width);
+        (0..self.depth).map(move |r| r * w + a.wrapping_add(r.wrapping_mul(b)) % w)
+    }
+
+    fn estimate_hash(&self, h: u64) -> u64 {
+        self.cells(h).map(|c| self.counts[c]).min().unwrap()
+    }
+
+    /// estimated number of times `value`, as displayed, was added
+    pub fn estimate(&self, value: &str) -> u64 {
+        self.estimate_hash(hash_str(value))
+    }
+
+    /// number of values added
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn add(&mut self, value: &str) {
+        let h = hash_str(value);
+        for c in self.cells(h).collect::<Vec<_>>() {
+            self.counts[c] += 1;
+        }
+        self.total += 1;
+        let est = self.estimate_hash(h);
+        self.offer(value, est);
+    }
+
+    fn offer(&mut self, value: &str, est: u64) {
+        if let Some(e) = self.heavy.get_mut(value) {
+            *e = est;
+            return;
+        }
+        if self.heavy.len() >= self.top {
+            match self.heavy.iter().min_by_key(|(_, e)| **e) {
+                Some((k, e)) if *e < est => {
+                    let k = k.clone();
+                    self.heavy.remove(&k);
+                }
+                _ => return,
+            }
+        }
+        self.heavy.insert(value.to_string(), est);
+    }
+
+    /// the most frequent values with their estimated counts, most frequent first
+    pub fn heavy_hitters(&self) -> Vec<(String, u64)> {
+        let mut v: Vec<(String, u64)> = self.heavy.iter().map(|(k, e)| (k.clone(), *e)).collect();
+        v.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        v
+    }
+}
+
+impl Sketch for CountMin {
+    fn update(&mut self, c: ColumnView) -> Result<(), String> {
+        for i in (0..c.len()).filter(|i| !is_missing(&c, *i)) {
+            match c {
+                ColumnView::S(x) => self.add(&x[i]),
+                _ => self.add(&c.fmt_value(i)),
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, other: &Self) -> Result<(), String> {
+        if (self.width, self.depth) != (other.width, other.depth) {
+            return Err(format!(
+                "cannot merge sketches of size {}x{} and {}x{}",
+                self.width, self.depth, other.width, other.depth
+            ));
+        }
+        for (c, o) in self.counts.iter_mut().zip(&other.counts) {
+            *c += o;
+        }
+        self.total += other.total;
+        let candidates: Vec<String> = self.heavy.keys().chain(other.heavy.keys()).cloned().collect();
+        self.heavy.clear();
+        for k in candidates {
+            let est = self.estimate(&k);
+            self.offer(&k, est);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::enum_df::Column;
+
+    #[test]
+    fn test_hyperloglog() {
+        let c = Column::I((0..100_000).map(|i| i % 50_000).collect());
+        let mut h = HyperLogLog::new(14).unwrap();
+        h.update(c.view()).unwrap();
+        assert!((h.estimate() / 50_000.0 - 1.0).abs() < 0.03, "{}", h.estimate());
+
+        let mut small = HyperLogLog::new(14).unwrap();
+        small.update(Column::D(vec![1.0, 2.0, f64::NAN, 1.0]).view()).unwrap();
+        assert_eq!(small.estimate().round(), 2.0);
+
+        let (mut a, mut b) = (HyperLogLog::new(14).unwrap(), HyperLogLog::new(14).unwrap());
+        a.update(c.slice(0, 30_000)).unwrap();
+        b.update(c.slice(30_000, 70_000)).unwrap();
+        a.merge(&b).unwrap();
+        assert_eq!(a.estimate(), h.estimate());
+        assert!(a.merge(&HyperLogLog::new(10).unwrap()).is_err());
+        assert!(HyperLogLog::new(3).is_err());
+        assert!(Column::irange(1000).approx_n_unique().abs_diff(1000) < 20);
+    }
+
+    #[test]
+    fn test_tdigest() {
+        let mut t = TDigest::new(100.0);
+        assert!(t.quantile(0.5).is_nan());
+        t.update(Column::frange(10_001).view()).unwrap();
+        assert_eq!(t.count(), 10_001);
+        assert!(t.centroids.len() < 200);
+        for q in [0.01, 0.25, 0.5, 0.9, 0.999] {
+            assert!((t.quantile(q) - q * 10_000.0).abs() < 20.0, "{} {}", q, t.quantile(q));
+        }
+        assert_eq!(t.quantile(0.0), 0.0);
+        assert_eq!(t.quantile(1.0), 10_000.0);
+
+        let mut a = TDigest::new(100.0);
+        let mut b = TDigest::new(100.0);
+        a.update(Column::D((0..5000).map(|i| i as f64).collect()).view()).unwrap();
+        b.update(Column::D((5000..10_001).map(|i| i as f64).collect()).view()).unwrap();
+        a.merge(&b).unwrap();
+        assert!((a.quantile(0.5) - 5000.0).abs() < 20.0);
+        assert!(a.update(Column::S(vec!["x".into()]).view()).is_err());
+        assert!((Column::irange(1001).approx_quantile(0.5).unwrap() - 500.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_count_min() {
+        let mut v: Vec<String> = (0..10_000).map(|i| format!("v{}", i)).collect();
+        v.extend(std::iter::repeat_n("hot".to_string(), 3000));
+        v.extend(std::iter::repeat_n("warm".to_string(), 1000));
+        let c = Column::S(v);
+        let mut s = CountMin::new(2000, 5, 2).unwrap();
+        s.update(c.view()).unwrap();
+        assert_eq!(s.total(), 14_000);
+        assert!(s.estimate("hot") >= 3000 && s.estimate("hot") < 3050);
+        let top: Vec<String> = s.heavy_hitters().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(top, vec!["hot", "warm"]);
+
+        let (mut a, mut b) = (CountMin::new(2000, 5, 2).unwrap(), CountMin::new(2000, 5, 2).unwrap());
+        a.update(c.slice(0, 11_500)).unwrap();
+        b.update(c.slice(11_500, 2500)).unwrap();
+        a.merge(&b).unwrap();
+        assert_eq!(a.heavy_hitters(), s.heavy_hitters());
+        assert!(a.merge(&CountMin::new(10, 5, 2).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Splitting `enum_df` rows into training and test sets for model evaluation.
+//!
+//! Splits are sets of row indices, turned into frames with `Split::frames`
+//! (which takes the rows with `DataFrame::take`). Shuffling uses the same
+//! seeded generator as `sample`, so a seed always gives the same split.
+
+use rand::seq::SliceRandom;
+
+use crate::enum_df::DataFrame;
+use crate::groupby;
+use crate::sample::{self, SampleSize};
+
+/// Row indices of one training and test set, each in ascending order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Split {
+    pub train: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl Split {
+    /// the (train, test) frames of `df`
+    pub fn frames(&self, df: &DataFrame) -> (DataFrame, DataFrame) {
+        (df.take(&self.train), df.take(&self.test))
+    }
+}
+
+fn rows(len: usize, shuffle: bool, seed: Option<u64>) -> Vec<usize> {
+    let mut idx: Vec<usize> = (0..len).collect();
+    if shuffle {
+        idx.shuffle(&mut sample::rng(seed));
+    }
+    idx
+}
+
+/// split the rows of `df` in two, `test` of them in the test set. Without
+/// `shuffle` the test set is the last rows.
+pub fn split_rows(df: &DataFrame, test: SampleSize, shuffle: bool, seed: Option<u64>) -> Result<Split, String> {
+    let n = test.rows(df.len())?;
+    if n > df.len() {
+        return Err(format!("cannot put {} of {} rows in the test set", n, df.len()));
+    }
+    let idx = rows(df.len(), shuffle, seed);
+    let (train, test) = idx.split_at(df.len() - n);
+    let (mut train, mut test) = (train.to_vec(), test.to_vec());
+    train.sort_unstable();
+    test.sort_unstable();
+    Ok(Split { train, test })
+}
+
+/// the (train, test) frames of `split_rows`
+pub fn train_test_split(
+    df: &DataFrame, test: SampleSize, shuffle: bool, seed: Option<u64>,
+) -> Result<(DataFrame, DataFrame), String> {
+    Ok(split_rows(df, test, shuffle, seed)?.frames(df))
+}
+
+/// Iterator over the splits of a cross-validation, each fold is the test set
+/// once and in the training set otherwise
+#[derive(Clone, Debug)]
+pub struct Folds {
+    folds: Vec<Vec<usize>>,
+    next: usize,
+}
+
+impl Folds {
+    fn new(mut folds: Vec<Vec<usize>>) -> Folds {
+        folds.iter_mut().for_each(|f| f.sort_unstable());
+        Folds { folds, next: 0 }
+    }
+
+    /// the (train, test) frames of each split
+    pub fn frames(self, df: &DataFrame) -> impl Iterator<Item = (DataFrame, DataFrame)> + '_ {
+        self.map(|s| s.frames(df))
+    }
+}
+
+impl Iterator for Folds {
+    type Item = Split;
+
+    fn next(&mut self) -> Option<Split> {
+        let test = self.folds.get(self.next)?.clone();
+        let mut train: Vec<usize> = self.folds.iter().enumerate()
+            .filter(|(i, _)| *i != self.next)
+            .flat_map(|(_, f)| f.iter().copied())
+            .collect();
+        train.sort_unstable();
+        self.next += 1;
+        Some(Split { train, test })
+    }
+}
+
+fn check_folds(len: usize, k: usize) -> Result<(), String> {
+    if k < 2 || k > len {
+        return Err(format!("cannot make {} folds of {} rows", k, len));
+    }
+    Ok(())
+}
+
+/// `k` folds of consecutive rows (after shuffling if asked), sizes differing
+/// by at most one
+pub fn k_fold(df: &DataFrame, k: usize, shuffle: bool, seed: Option<u64>) -> Result<Folds, String> {
+    check_folds(df.len(), k)?;
+    let idx = rows(df.len(), shuffle, seed);
+    let (size, extra) = (df.len() / k, df.len() % k);
+    let mut start = 0;
+    let folds = (0..k).map(|i| {
+        let end = start + size + (i < extra) as usize;
+        let f = idx[start..end].to_vec();
+        start = end;
+        f
+    }).collect();
+    Ok(Folds::new(folds))
+}
+
+/// `k` folds that each hold about the same share of every group of the `key`
+/// column as the whole frame
+pub fn stratified_k_fold(df: &DataFrame, key: &str, k: usize, shuffle: bool, seed: Option<u64>) -> Result<Folds, String> {
+    check_folds(df.len(), k)?;
+    let mut rng = sample::rng(seed);
+    let mut folds = vec![Vec::new(); k];
+    // deal each group's rows round robin, carrying on from where the last
+    // group stopped so the folds stay balanced in size
+    let mut at = 0;
+    for mut g in groupby::group_rows(df, &[key])? {
+        if shuffle {
+            g.shuffle(&mut rng);
+        }
+        for r in g {
+            folds[at % k].push(r);
+            at += 1;
+        }
+    }
+    Ok(Folds::new(folds))
+}
+
+/// Iterator over the splits of `time_series_split`
+#[derive(Clone, Debug)]
+pub struct TimeSeriesSplits {
+    len: usize,
+    n_splits: usize,
+    test_size: usize,
+    next: usize,
+}
+
+impl TimeSeriesSplits {
+    /// the (train, test) frames of each split
+    pub fn frames(self, df: &DataFrame) -> impl Iterator<Item = (DataFrame, DataFrame)> + '_ {
+        self.map(|s| s.frames(df))
+    }
+}
+
+impl Iterator for TimeSeriesSplits {
+    type Item = Split;
+
+    fn next(&mut self) -> Option<Split> {
+        if self.next == self.n_splits {
+            return None;
+        }
+        // the splits' test sets end together at the last row
+        let end = self.len - (self.n_splits - 1 - self.next) * self.test_size;
+        self.next += 1;
+        Some(Split { train: (0..end - self.test_size).collect(), test: (end - self.test_size..end).collect() })
+    }
+}
+
+/// `n_splits` splits for time ordered rows, each testing on the `test_size`
+/// rows (by default `len / (n_splits + 1)`) after a training set of every
+/// earlier row, so the model never sees the future
+pub fn time_series_split(df: &DataFrame, n_splits: usize, test_size: Option<usize>) -> Result<TimeSeriesSplits, String> {
+    let len = df.len();
+    let cannot = |t: &dyn std::fmt::Display| format!("cannot make {} splits of {} test rows from {} rows", n_splits, t, len);
+    let test_size = match test_size {
+        Some(t) => t,
+        None => len / n_splits.checked_add(1).ok_or_else(|| cannot(&"any"))?,
+    };
+    match n_splits.checked_mul(test_size) {
+        Some(total) if n_splits > 0 && test_size > 0 && total < len => (),
+        _ => return Err(cannot(&test_size)),
+    }
+    Ok(TimeSeriesSplits { len, n_splits, test_size, next: 0 })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+    use crate::enum_df::Column;
+
+    #[test]
+    fn test_train_test_split() {
+        let df = df! { "a" => Column::irange(10) };
+        let s = split_rows(&df, SampleSize::Frac(0.3), false, None).unwrap();
+        assert_eq!(s.test, vec![7, 8, 9]);
+        assert_eq!(s.train, (0..7).collect::<Vec<_>>());
+
+        let s = split_rows(&df, SampleSize::N(4), true, Some(5)).unwrap();
+        assert_eq!(s, split_rows(&df, SampleSize::N(4), true, Some(5)).unwrap());
+        let mut all = [s.train.clone(), s.test.clone()].concat();
+        all.sort();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+
+        let (train, test) = train_test_split(&df, SampleSize::N(4), true, Some(5)).unwrap();
+        assert_eq!((train.len(), test.len()), (6, 4));
+        assert!(split_rows(&df, SampleSize::N(11), false, None).is_err());
+    }
+
+    #[test]
+    fn test_k_fold() {
+        let df = df! { "a" => Column::irange(10) };
+        let tests: Vec<Vec<usize>> = k_fold(&df, 3, false, None).unwrap().map(|s| s.test).collect();
+        assert_eq!(tests, vec![vec![0, 1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]);
+
+        let splits: Vec<Split> = k_fold(&df, 3, true, Some(1)).unwrap().collect();
+        let mut seen: Vec<usize> = splits.iter().flat_map(|s| s.test.clone()).collect();
+        seen.sort();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+        assert!(splits.iter().all(|s| s.train.len() + s.test.len() == 10 && s.train.iter().all(|r| !s.test.contains(r))));
+
+        let sizes: Vec<(usize, usize)> = k_fold(&df, 5, false, None).unwrap().frames(&df).map(|(a, b)| (a.len(), b.len())).collect();
+        assert_eq!(sizes, vec![(8, 2); 5]);
+        assert!(k_fold(&df, 1, false, None).is_err());
+        assert!(k_fold(&df, 11, false, None).is_err());
+    }
+
+    #[test]
+    fn test_stratified_k_fold() {
+        let k: Vec<&str> = (0..12).map(|i| if i % 4 == 0 { "rare" } else { "common" }).collect();
+        let df = df! { "k" => k };
+        for s in stratified_k_fold(&df, "k", 3, true, Some(2)).unwrap() {
+            let (_, test) = s.frames(&df);
+            let Some(Column::S(v)) = test.get("k") else { panic!("expected S column") };
+            assert_eq!(v.iter().filter(|x| *x == "rare").count(), 1);
+            assert_eq!(v.len(), 4);
+        }
+        assert!(stratified_k_fold(&df, "missing", 3, false, None).is_err());
+    }
+
+    #[test]
+    fn test_time_series_split() {
+        let df = df! { "a" => Column::irange(10) };
+        let s: Vec<Split> = time_series_split(&df, 3, None).unwrap().collect();
+        assert_eq!(s.len(), 3);
+        assert_eq!(s[0], Split { train: (0..4).collect(), test: vec![4, 5] });
+        assert_eq!(s[2], Split { train: (0..8).collect(), test: vec![8, 9] });
+        let mut s = time_series_split(&df, 2, Some(3)).unwrap();
+        assert_eq!(s.next().unwrap().test, vec![4, 5, 6]);
+        let sizes: Vec<(usize, usize)> = s.frames(&df).map(|(a, b)| (a.len(), b.len())).collect();
+        assert_eq!(sizes, vec![(7, 3)]);
+        assert!(time_series_split(&df, 4, Some(3)).is_err());
+        // sizes that overflow are errors rather than wrapping
+        assert!(time_series_split(&df, usize::MAX, None).is_err());
+        assert!(time_series_split(&df, 2, Some(usize::MAX / 2 + 1)).is_err());
+    }
+}
@@ -0,0 +1,1259 @@
+//! SQL queries over registered `enum_df` frames.
+//!
+//! Supports a single SELECT statement of the form
+//!
+//! ```text
+//! SELECT * | expr [AS name], ...
+//! FROM table [alias]
+//! [[INNER] JOIN table [alias] ON a.x = b.y [AND ...]] ...
+//! [WHERE expr]
+//! [GROUP BY column, ...]
+//! [ORDER BY expr [ASC | DESC], ...]
+//! [LIMIT n [OFFSET m]]
+//! ```
+//!
+//! Expressions can use columns (optionally qualified by table or alias),
+//! integer, float and 'string' literals, `+ - * /`, comparisons and
+//! `AND / OR / NOT`. The aggregates `count`, `sum`, `avg`, `min` and `max`
+//! take a single column (or `*` for count). `min` and `max` of an integer
+//! column are integers, as is a `sum` that fits in `i32`. Columns cannot hold
+//! nulls, so only inner joins are supported.
+//!
+//! ```
+//! use rusttest::df;
+//! use rusttest::sql::SqlContext;
+//!
+//! let mut ctx = SqlContext::new();
+//! ctx.register("t", df! { "k" => ["a", "b", "a"], "v" => [1, 2, 3] });
+//! let out = ctx.execute("SELECT k, sum(v) AS total FROM t GROUP BY k ORDER BY total DESC").unwrap();
+//! assert_eq!(out.len(), 2);
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::enum_df::{Column, DataFrame};
+use crate::groupby::{Agg, GroupBy};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlError {
+    Parse(String),
+    Execution(String),
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlError::Parse(m) => write!(f, "parse error: {}", m),
+            SqlError::Execution(m) => write!(f, "execution error: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for SqlError {}
+
+type Result<T> = std::result::Result<T, SqlError>;
+
+fn parse_err<T>(msg: impl Into<String>) -> Result<T> {
+    Err(SqlError::Parse(msg.into()))
+}
+
+fn exec_err<T>(msg: impl Into<String>) -> Result<T> {
+    Err(SqlError::Execution(msg.into()))
+}
+
+// ---- tokens ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    // "quoted" identifier, never treated as a keyword
+    Quoted(String),
+    Number(String),
+    Str(String),
+    Sym(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) | Token::Number(s) => write!(f, "{}", s),
+            Token::Quoted(s) => write!(f, "\"{}\"", s),
+            Token::Str(s) => write!(f, "'{}'", s),
+            Token::Sym(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 16] = ["<=", ">=", "<>", "!=", ",", "(", ")", "*", ".", "=", "<", ">", "+", "-", "/", ";"];
+
+const KEYWORDS: [&str; 17] = [
+    "SELECT", "FROM", "WHERE", "GROUP", "BY", "ORDER", "LIMIT", "OFFSET", "JOIN", "INNER",
+    "LEFT", "ON", "AS", "AND", "OR", "NOT", "ASC",
+];
+
+fn is_keyword(s: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(s)) || s.eq_ignore_ascii_case("DESC")
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = sql.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '\'' || c == '"' {
+            // quote char doubled inside the literal escapes it
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return parse_err("unterminated quoted literal"),
+                    Some(q) if *q == c && chars.get(i + 1) == Some(&c) => {
+                        s.push(c);
+                        i += 2;
+                    }
+                    Some(q) if *q == c => break,
+                    Some(x) => {
+                        s.push(*x);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(if c == '\'' { Token::Str(s) } else { Token::Quoted(s) });
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(sym) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) else {
+                return parse_err(format!("unexpected character '{}'", c));
+            };
+            tokens.push(Token::Sym(sym));
+            i += sym.len();
+        }
+    }
+    Ok(tokens)
+}
+
+// ---- syntax tree ----
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinOp {
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Eq => "=",
+            BinOp::Ne => "<>",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "AND",
+            BinOp::Or => "OR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Col(Option<String>, String),
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Bin(Box<Expr>, BinOp, Box<Expr>),
+    // the function name as written, lowercased, and None for count(*)
+    Agg(Agg, String, Option<Box<Expr>>),
+}
+
+impl Expr {
+    fn has_agg(&self) -> bool {
+        match self {
+            Expr::Agg(..) => true,
+            Expr::Neg(e) | Expr::Not(e) => e.has_agg(),
+            Expr::Bin(a, _, b) => a.has_agg() || b.has_agg(),
+            _ => false,
+        }
+    }
+
+    /// text used to name an output column that has no alias
+    fn label(&self) -> String {
+        match self {
+            Expr::Col(_, n) => n.clone(),
+            Expr::Int(x) => x.to_string(),
+            Expr::Num(x) => x.to_string(),
+            Expr::Str(s) => format!("'{}'", s),
+            Expr::Neg(e) => format!("-{}", e.label()),
+            Expr::Not(e) => format!("NOT {}", e.label()),
+            Expr::Bin(a, op, b) => format!("{} {} {}", a.label(), op.symbol(), b.label()),
+            Expr::Agg(_, f, None) => format!("{}(*)", f),
+            Expr::Agg(_, f, Some(e)) => format!("{}({})", f, e.label()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SelectItem {
+    Wildcard,
+    Expr(Expr, Option<String>),
+}
+
+#[derive(Debug)]
+struct TableRef {
+    name: String,
+    alias: String,
+}
+
+#[derive(Debug)]
+struct Query {
+    items: Vec<SelectItem>,
+    from: TableRef,
+    joins: Vec<(TableRef, Expr)>,
+    filter: Option<Expr>,
+    group_by: Vec<Expr>,
+    order_by: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+// ---- parser ----
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(t) => format!("'{}'", t),
+            None => "end of query".to_string(),
+        }
+    }
+
+    fn is_kw(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s.eq_ignore_ascii_case(kw))
+    }
+
+    fn eat_kw(&mut self, kw: &str) -> bool {
+        let found = self.is_kw(kw);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_kw(&mut self, kw: &str) -> Result<()> {
+        if !self.eat_kw(kw) {
+            return parse_err(format!("expected {}, found {}", kw, self.found()));
+        }
+        Ok(())
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Sym(s)) if *s == sym);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> Result<()> {
+        if !self.eat_sym(sym) {
+            return parse_err(format!("expected '{}', found {}", sym, self.found()));
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(s)) if !is_keyword(s) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            Some(Token::Quoted(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => parse_err(format!("expected identifier, found {}", self.found())),
+        }
+    }
+
+    /// optional `[AS] name` after a table or select item
+    fn alias(&mut self) -> Result<Option<String>> {
+        if self.eat_kw("AS") {
+            return self.ident().map(Some);
+        }
+        match self.peek() {
+            Some(Token::Ident(s)) if !is_keyword(s) => self.ident().map(Some),
+            Some(Token::Quoted(_)) => self.ident().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn usize(&mut self) -> Result<usize> {
+        match self.next() {
+            Some(Token::Number(n)) => n.parse().or_else(|_| parse_err(format!("invalid count '{}'", n))),
+            t => parse_err(format!("expected a number, found {}", t.map_or("end of query".to_string(), |t| format!("'{}'", t)))),
+        }
+    }
+
+    fn query(&mut self) -> Result<Query> {
+        self.expect_kw("SELECT")?;
+        let mut items = Vec::new();
+        if self.eat_sym("*") {
+            items.push(SelectItem::Wildcard);
+        } else {
+            loop {
+                let e = self.expr()?;
+                let alias = self.alias()?;
+                items.push(SelectItem::Expr(e, alias));
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+        }
+
+        self.expect_kw("FROM")?;
+        let from = self.table_ref()?;
+        let mut joins = Vec::new();
+        loop {
+            if self.is_kw("LEFT") {
+                return parse_err("only inner joins are supported, columns cannot hold nulls");
+            }
+            let inner = self.eat_kw("INNER");
+            if !self.eat_kw("JOIN") {
+                if inner {
+                    return parse_err(format!("expected JOIN, found {}", self.found()));
+                }
+                break;
+            }
+            let t = self.table_ref()?;
+            self.expect_kw("ON")?;
+            joins.push((t, self.expr()?));
+        }
+
+        let filter = if self.eat_kw("WHERE") { Some(self.expr()?) } else { None };
+
+        let mut group_by = Vec::new();
+        if self.eat_kw("GROUP") {
+            self.expect_kw("BY")?;
+            loop {
+                group_by.push(self.expr()?);
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.eat_kw("ORDER") {
+            self.expect_kw("BY")?;
+            loop {
+                let e = self.expr()?;
+                let desc = self.eat_kw("DESC");
+                if !desc {
+                    self.eat_kw("ASC");
+                }
+                order_by.push((e, desc));
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        let mut offset = 0;
+        if self.eat_kw("LIMIT") {
+            limit = Some(self.usize()?);
+            if self.eat_kw("OFFSET") {
+                offset = self.usize()?;
+            }
+        }
+
+        self.eat_sym(";");
+        if self.peek().is_some() {
+            return parse_err(format!("unexpected {}", self.found()));
+        }
+        Ok(Query { items, from, joins, filter, group_by, order_by, limit, offset })
+    }
+
+    fn table_ref(&mut self) -> Result<TableRef> {
+        let name = self.ident()?;
+        let alias = self.alias()?.unwrap_or_else(|| name.clone());
+        Ok(TableRef { name, alias })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut e = self.and()?;
+        while self.eat_kw("OR") {
+            e = Expr::Bin(Box::new(e), BinOp::Or, Box::new(self.and()?));
+        }
+        Ok(e)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut e = self.not()?;
+        while self.eat_kw("AND") {
+            e = Expr::Bin(Box::new(e), BinOp::And, Box::new(self.not()?));
+        }
+        Ok(e)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat_kw("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.cmp()
+    }
+
+    fn cmp(&mut self) -> Result<Expr> {
+        let e = self.add()?;
+        let op = match self.peek() {
+            Some(Token::Sym("=")) => BinOp::Eq,
+            Some(Token::Sym("<>" | "!=")) => BinOp::Ne,
+            Some(Token::Sym("<")) => BinOp::Lt,
+            Some(Token::Sym("<=")) => BinOp::Le,
+            Some(Token::Sym(">")) => BinOp::Gt,
+            Some(Token::Sym(">=")) => BinOp::Ge,
+            _ => return Ok(e),
+        };
+        self.pos += 1;
+        Ok(Expr::Bin(Box::new(e), op, Box::new(self.add()?)))
+    }
+
+    fn add(&mut self) -> Result<Expr> {
+        let mut e = self.mul()?;
+        loop {
+            let op = if self.eat_sym("+") {
+                BinOp::Add
+            } else if self.eat_sym("-") {
+                BinOp::Sub
+            } else {
+                return Ok(e);
+            };
+            e = Expr::Bin(Box::new(e), op, Box::new(self.mul()?));
+        }
+    }
+
+    fn mul(&mut self) -> Result<Expr> {
+        let mut e = self.unary()?;
+        loop {
+            let op = if self.eat_sym("*") {
+                BinOp::Mul
+            } else if self.eat_sym("/") {
+                BinOp::Div
+            } else {
+                return Ok(e);
+            };
+            e = Expr::Bin(Box::new(e), op, Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat_sym("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.peek().cloned() {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                if let Ok(i) = n.parse::<i64>() {
+                    return Ok(Expr::Int(i));
+                }
+                n.parse::<f64>().map(Expr::Num).or_else(|_| parse_err(format!("invalid number '{}'", n)))
+            }
+            Some(Token::Str(s)) => {
+                self.pos += 1;
+                Ok(Expr::Str(s))
+            }
+            Some(Token::Sym("(")) => {
+                self.pos += 1;
+                let e = self.expr()?;
+                self.expect_sym(")")?;
+                Ok(e)
+            }
+            _ => {
+                let name = self.ident()?;
+                if self.eat_sym("(") {
+                    let Some(agg) = Agg::parse(&name) else {
+                        return parse_err(format!("unknown function '{}'", name));
+                    };
+                    let arg = if self.eat_sym("*") {
+                        if agg != Agg::Count {
+                            return parse_err(format!("{}(*) is not supported", name));
+                        }
+                        None
+                    } else {
+                        Some(Box::new(self.expr()?))
+                    };
+                    self.expect_sym(")")?;
+                    return Ok(Expr::Agg(agg, name.to_ascii_lowercase(), arg));
+                }
+                if self.eat_sym(".") {
+                    return Ok(Expr::Col(Some(name), self.ident()?));
+                }
+                Ok(Expr::Col(None, name))
+            }
+        }
+    }
+}
+
+fn parse(sql: &str) -> Result<Query> {
+    Parser { tokens: tokenize(sql)?, pos: 0 }.query()
+}
+
+// ---- values ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Scalar {
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Scalar {
+    fn at(c: &Column, i: usize) -> Scalar {
+        match c {
+            Column::F(x) => Scalar::Num(x[i] as f64),
+            Column::D(x) => Scalar::Num(x[i]),
+            Column::I(x) => Scalar::Int(x[i] as i64),
+            Column::S(x) => Scalar::Str(x[i].clone()),
+            // nested values compare by their display form
+            c => Scalar::Str(c.view().fmt_value(i)),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Scalar::Int(x) => Some(*x as f64),
+            Scalar::Num(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Scalar::Int(_) | Scalar::Num(_) => "number",
+            Scalar::Str(_) => "string",
+            Scalar::Bool(_) => "boolean",
+        }
+    }
+
+    fn compare(&self, other: &Scalar) -> Result<Ordering> {
+        match (self, other) {
+            (Scalar::Int(a), Scalar::Int(b)) => Ok(a.cmp(b)),
+            (Scalar::Str(a), Scalar::Str(b)) => Ok(a.cmp(b)),
+            (Scalar::Bool(a), Scalar::Bool(b)) => Ok(a.cmp(b)),
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(x), Some(y)) => Ok(x.total_cmp(&y)),
+                _ => exec_err(format!("cannot compare {} with {}", a.type_name(), b.type_name())),
+            },
+        }
+    }
+
+    /// ordering used for sorting, where values of a column always share a type
+    fn sort_cmp(&self, other: &Scalar) -> Ordering {
+        self.compare(other).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// hashable form of a join key, numbers compare equal across int and float
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum HashKey {
+    Num(u64),
+    Str(String),
+    Bool(bool),
+}
+
+impl From<Scalar> for HashKey {
+    fn from(s: Scalar) -> Self {
+        match s {
+            // adding zero folds -0.0 into 0.0
+            Scalar::Int(x) => HashKey::Num((x as f64 + 0.0).to_bits()),
+            Scalar::Num(x) => HashKey::Num((x + 0.0).to_bits()),
+            Scalar::Str(x) => HashKey::Str(x),
+            Scalar::Bool(x) => HashKey::Bool(x),
+        }
+    }
+}
+
+/// build a column from computed values: i32 if every value is an integer in
+/// range, f64 for other numbers, 0/1 for booleans
+fn column_from(vals: Vec<Scalar>) -> Result<Column> {
+    if vals.iter().all(|v| matches!(v, Scalar::Str(_))) && !vals.is_empty() {
+        return Ok(Column::S(vals.into_iter().map(|v| match v {
+            Scalar::Str(s) => s,
+            _ => unreachable!(),
+        }).collect()));
+    }
+    if vals.iter().all(|v| matches!(v, Scalar::Bool(_))) && !vals.is_empty() {
+        return Ok(Column::I(vals.iter().map(|v| (*v == Scalar::Bool(true)) as i32).collect()));
+    }
+    let ints: Option<Vec<i32>> = vals.iter().map(|v| match v {
+        Scalar::Int(x) => i32::try_from(*x).ok(),
+        _ => None,
+    }).collect();
+    if let Some(ints) = ints.filter(|v| !v.is_empty()) {
+        return Ok(Column::I(ints));
+    }
+    vals.iter().map(|v| v.as_f64().map_or_else(
+        || exec_err(format!("cannot mix {} and number values in a column", v.type_name())),
+        Ok,
+    )).collect::<Result<Vec<_>>>().map(Column::D)
+}
+
+// ---- execution ----
+
+/// Frame being queried, columns are stored as `qualifier.name` and `cols`
+/// keeps the (qualifier, name) pairs for resolving references
+struct Working {
+    df: DataFrame,
+    cols: Vec<(String, String)>,
+}
+
+impl Working {
+    fn new(df: &DataFrame, qualifier: &str) -> Working {
+        let mut w = Working { df: DataFrame::new(), cols: Vec::new() };
+        for (n, c) in df.iter() {
+            w.df.addcol(&format!("{}.{}", qualifier, n), c.clone()).expect("columns of a frame have equal length");
+            w.cols.push((qualifier.to_string(), n.to_string()));
+        }
+        w
+    }
+
+    fn resolve(&self, q: Option<&str>, name: &str) -> Result<Option<String>> {
+        let mut found = self.cols.iter().filter(|(cq, cn)| cn == name && q.is_none_or(|q| q == cq));
+        let Some((cq, cn)) = found.next() else {
+            return Ok(None);
+        };
+        if found.next().is_some() {
+            return exec_err(format!("column reference '{}' is ambiguous", name));
+        }
+        Ok(Some(format!("{}.{}", cq, cn)))
+    }
+
+    fn column(&self, q: Option<&str>, name: &str) -> Result<&Column> {
+        match self.resolve(q, name)? {
+            Some(key) => Ok(self.df.get(&key).unwrap()),
+            None => exec_err(match q {
+                Some(q) => format!("no column named '{}.{}'", q, name),
+                None => format!("no column named '{}'", name),
+            }),
+        }
+    }
+
+    /// output name for a column, qualified only if the bare name is ambiguous
+    fn display_name(&self, q: &str, name: &str) -> String {
+        match self.cols.iter().filter(|(_, n)| n == name).count() {
+            1 => name.to_string(),
+            _ => format!("{}.{}", q, name),
+        }
+    }
+
+    fn take(&self, idx: &[usize]) -> Working {
+        Working { df: self.df.take(idx), cols: self.cols.clone() }
+    }
+}
+
+/// expression with column references resolved against a frame
+enum Bound<'a> {
+    Col(&'a Column),
+    Lit(Scalar),
+    Neg(Box<Bound<'a>>),
+    Not(Box<Bound<'a>>),
+    Bin(Box<Bound<'a>>, BinOp, Box<Bound<'a>>),
+}
+
+fn bind<'a>(e: &Expr, w: &'a Working) -> Result<Bound<'a>> {
+    Ok(match e {
+        Expr::Col(q, n) => Bound::Col(w.column(q.as_deref(), n)?),
+        Expr::Int(x) => Bound::Lit(Scalar::Int(*x)),
+        Expr::Num(x) => Bound::Lit(Scalar::Num(*x)),
+        Expr::Str(s) => Bound::Lit(Scalar::Str(s.clone())),
+        Expr::Neg(e) => Bound::Neg(Box::new(bind(e, w)?)),
+        Expr::Not(e) => Bound::Not(Box::new(bind(e, w)?)),
+        Expr::Bin(a, op, b) => Bound::Bin(Box::new(bind(a, w)?), *op, Box::new(bind(b, w)?)),
+        Expr::Agg(..) => return exec_err(format!("aggregate {} is not allowed here", e.label())),
+    })
+}
+
+fn eval(b: &Bound, row: usize) -> Result<Scalar> {
+    Ok(match b {
+        Bound::Col(c) => Scalar::at(c, row),
+        Bound::Lit(s) => s.clone(),
+        Bound::Neg(e) => match eval(e, row)? {
+            Scalar::Int(x) => Scalar::Int(-x),
+            Scalar::Num(x) => Scalar::Num(-x),
+            v => return exec_err(format!("cannot negate a {}", v.type_name())),
+        },
+        Bound::Not(e) => match eval(e, row)? {
+            Scalar::Bool(x) => Scalar::Bool(!x),
+            v => return exec_err(format!("NOT needs a boolean, found a {}", v.type_name())),
+        },
+        Bound::Bin(a, op, b) => binary(eval(a, row)?, *op, eval(b, row)?)?,
+    })
+}
+
+fn binary(a: Scalar, op: BinOp, b: Scalar) -> Result<Scalar> {
+    let ord = |a: &Scalar, b: &Scalar| a.compare(b);
+    Ok(match op {
+        BinOp::Eq => Scalar::Bool(ord(&a, &b)? == Ordering::Equal),
+        BinOp::Ne => Scalar::Bool(ord(&a, &b)? != Ordering::Equal),
+        BinOp::Lt => Scalar::Bool(ord(&a, &b)? == Ordering::Less),
+        BinOp::Le => Scalar::Bool(ord(&a, &b)? != Ordering::Greater),
+        BinOp::Gt => Scalar::Bool(ord(&a, &b)? == Ordering::Greater),
+        BinOp::Ge => Scalar::Bool(ord(&a, &b)? != Ordering::Less),
+        BinOp::And | BinOp::Or => match (a, b) {
+            (Scalar::Bool(x), Scalar::Bool(y)) => Scalar::Bool(if op == BinOp::And { x && y } else { x || y }),
+            (x, y) => return exec_err(format!("{} needs booleans, found {} and {}", op.symbol(), x.type_name(), y.type_name())),
+        },
+        _ => {
+            if let (Scalar::Int(x), Scalar::Int(y), true) = (&a, &b, op != BinOp::Div) {
+                let r = match op {
+                    BinOp::Add => x.checked_add(*y),
+                    BinOp::Sub => x.checked_sub(*y),
+                    _ => x.checked_mul(*y),
+                };
+                return r.map(Scalar::Int).map_or_else(|| exec_err("integer overflow"), Ok);
+            }
+            let (Some(x), Some(y)) = (a.as_f64(), b.as_f64()) else {
+                return exec_err(format!("cannot apply {} to {} and {}", op.symbol(), a.type_name(), b.type_name()));
+            };
+            Scalar::Num(match op {
+                BinOp::Add => x + y,
+                BinOp::Sub => x - y,
+                BinOp::Mul => x * y,
+                _ => x / y,
+            })
+        }
+    })
+}
+
+/// split `a AND b AND ...` into its parts
+fn conjuncts(e: &Expr) -> Vec<&Expr> {
+    match e {
+        Expr::Bin(a, BinOp::And, b) => {
+            let mut v = conjuncts(a);
+            v.extend(conjuncts(b));
+            v
+        }
+        e => vec![e],
+    }
+}
+
+fn hash_join(left: Working, right: Working, on: &Expr) -> Result<Working> {
+    let mut lkeys = Vec::new();
+    let mut rkeys = Vec::new();
+    for c in conjuncts(on) {
+        let Expr::Bin(a, BinOp::Eq, b) = c else {
+            return exec_err("join conditions must be equalities between columns");
+        };
+        let (Expr::Col(aq, an), Expr::Col(bq, bn)) = (a.as_ref(), b.as_ref()) else {
+            return exec_err("join conditions must be equalities between columns");
+        };
+        if left.resolve(aq.as_deref(), an)?.is_some() {
+            lkeys.push(left.column(aq.as_deref(), an)?);
+            rkeys.push(right.column(bq.as_deref(), bn)?);
+        } else {
+            lkeys.push(left.column(bq.as_deref(), bn)?);
+            rkeys.push(right.column(aq.as_deref(), an)?);
+        }
+    }
+    let key = |cols: &[&Column], i: usize| cols.iter().map(|c| HashKey::from(Scalar::at(c, i))).collect::<Vec<_>>();
+
+    let mut index: HashMap<Vec<HashKey>, Vec<usize>> = HashMap::new();
+    for i in 0..right.df.len() {
+        index.entry(key(&rkeys, i)).or_default().push(i);
+    }
+    let mut li = Vec::new();
+    let mut ri = Vec::new();
+    for i in 0..left.df.len() {
+        if let Some(m) = index.get(&key(&lkeys, i)) {
+            li.extend(std::iter::repeat_n(i, m.len()));
+            ri.extend(m);
+        }
+    }
+
+    let (l, r) = (left.take(&li), right.take(&ri));
+    let mut out = Working { df: l.df, cols: l.cols };
+    for ((q, n), (key, c)) in r.cols.into_iter().zip(r.df.into_columns()) {
+        out.df.addcol(&key, c).expect("joined sides have equal length");
+        out.cols.push((q, n));
+    }
+    Ok(out)
+}
+
+/// sort row indices by the given keys, each with a descending flag
+fn argsort(keys: &[(Vec<Scalar>, bool)], n: usize) -> Vec<usize> {
+    let mut idx: Vec<usize> = (0..n).collect();
+    idx.sort_by(|a, b| {
+        keys.iter()
+            .map(|(k, desc)| {
+                let o = k[*a].sort_cmp(&k[*b]);
+                if *desc { o.reverse() } else { o }
+            })
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+    idx
+}
+
+fn window(idx: Vec<usize>, q: &Query) -> Vec<usize> {
+    idx.into_iter().skip(q.offset).take(q.limit.unwrap_or(usize::MAX)).collect()
+}
+
+/// name each select item gets in the output
+fn item_name(e: &Expr, alias: &Option<String>) -> String {
+    alias.clone().unwrap_or_else(|| e.label())
+}
+
+fn add_output(df: &mut DataFrame, name: &str, c: Column) -> Result<()> {
+    if df.get(name).is_some() {
+        return exec_err(format!("duplicate output column '{}', use AS to rename", name));
+    }
+    df.addcol(name, c).expect("output columns have equal length");
+    Ok(())
+}
+
+fn execute_plain(w: Working, q: &Query) -> Result<DataFrame> {
+    let mut keys = Vec::new();
+    for (e, desc) in &q.order_by {
+        // ORDER BY may name a select item by its alias, input columns win
+        let aliased = match e {
+            Expr::Col(None, n) if w.resolve(None, n)?.is_none() => q.items.iter().find_map(|it| match it {
+                SelectItem::Expr(ie, Some(a)) if a == n => Some(ie),
+                _ => None,
+            }),
+            _ => None,
+        };
+        let e = aliased.unwrap_or(e);
+        let b = bind(e, &w)?;
+        keys.push(((0..w.df.len()).map(|i| eval(&b, i)).collect::<Result<Vec<_>>>()?, *desc));
+    }
+    let idx = window(argsort(&keys, w.df.len()), q);
+
+    let mut out = DataFrame::new();
+    for it in &q.items {
+        match it {
+            SelectItem::Wildcard => {
+                for ((cq, cn), (_, c)) in w.cols.iter().zip(w.df.iter()) {
+                    add_output(&mut out, &w.display_name(cq, cn), c.take(&idx))?;
+                }
+            }
+            SelectItem::Expr(e, alias) => {
+                let c = match bind(e, &w)? {
+                    Bound::Col(c) => c.take(&idx),
+                    b => column_from(idx.iter().map(|i| eval(&b, *i)).collect::<Result<_>>()?)?,
+                };
+                add_output(&mut out, &item_name(e, alias), c)?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn execute_grouped(w: Working, q: &Query) -> Result<DataFrame> {
+    let mut keys = Vec::new();
+    for e in &q.group_by {
+        let Expr::Col(cq, cn) = e else {
+            return exec_err("GROUP BY only supports columns");
+        };
+        match w.resolve(cq.as_deref(), cn)? {
+            // a repeated key groups the same
+            Some(k) if keys.contains(&k) => (),
+            Some(k) => keys.push(k),
+            None => return exec_err(format!("no column named '{}'", cn)),
+        }
+    }
+
+    // each output or sort column is either a key or an aggregate, by position
+    #[derive(Clone, Copy)]
+    enum Out {
+        Key(usize),
+        Agg(usize),
+    }
+    let mut aggs: Vec<(String, Agg)> = Vec::new();
+    // a repeated aggregate is computed once
+    let mut add_agg = |agg: Agg, arg: Option<&Expr>| -> Result<Out> {
+        let col = match arg {
+            None => match w.df.names().next() {
+                Some(n) => n.to_string(),
+                None => return exec_err("count(*) over a table with no columns"),
+            },
+            Some(Expr::Col(cq, cn)) => match w.resolve(cq.as_deref(), cn)? {
+                Some(k) => k,
+                None => return exec_err(format!("no column named '{}'", cn)),
+            },
+            Some(_) => return exec_err(format!("{} only supports a column argument", agg.name())),
+        };
+        Ok(Out::Agg(match aggs.iter().position(|(c, a)| *c == col && *a == agg) {
+            Some(i) => i,
+            None => {
+                aggs.push((col, agg));
+                aggs.len() - 1
+            }
+        }))
+    };
+    let key = |cq: &Option<String>, cn: &str| -> Result<Option<Out>> {
+        let k = w.resolve(cq.as_deref(), cn)?;
+        Ok(keys.iter().position(|x| Some(x) == k.as_ref()).map(Out::Key))
+    };
+
+    let mut outs = Vec::new();
+    for it in &q.items {
+        let SelectItem::Expr(e, alias) = it else {
+            return exec_err("SELECT * cannot be used with GROUP BY or aggregates");
+        };
+        let out = match e {
+            Expr::Col(cq, cn) => match key(cq, cn)? {
+                Some(o) => o,
+                None => return exec_err(format!("column '{}' must appear in GROUP BY or an aggregate", cn)),
+            },
+            Expr::Agg(agg, _, arg) => add_agg(*agg, arg.as_deref())?,
+            _ => return exec_err(format!("'{}' must be a grouped column or an aggregate", e.label())),
+        };
+        outs.push((out, item_name(e, alias)));
+    }
+
+    // ORDER BY refers to an output column by name, or to a grouped column or
+    // aggregate whether selected or not
+    let mut sorts = Vec::new();
+    for (e, desc) in &q.order_by {
+        let by_name = |n: &str| outs.iter().find(|(_, name)| name == n).map(|(o, _)| *o);
+        let o = match e {
+            Expr::Col(None, n) if by_name(n).is_some() => by_name(n),
+            Expr::Col(cq, cn) => key(cq, cn)?,
+            Expr::Agg(agg, _, arg) => Some(add_agg(*agg, arg.as_deref())?),
+            e => by_name(&e.label()),
+        };
+        match o {
+            Some(o) => sorts.push((o, *desc)),
+            None => return exec_err(format!("ORDER BY '{}' must be a grouped column or an aggregate", e.label())),
+        }
+    }
+
+    let key_refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let agg_refs: Vec<(&str, Agg)> = aggs.iter().map(|(c, a)| (c.as_str(), *a)).collect();
+    let mut g = GroupBy::new(&key_refs, &agg_refs).map_err(SqlError::Execution)?;
+    g.update(&w.df).map_err(SqlError::Execution)?;
+    let agg_name = |i: usize| format!("{}_{}", aggs[i].0, aggs[i].1.name());
+    let grouped = if keys.is_empty() && g.n_groups() == 0 {
+        // aggregates without GROUP BY give one row even over no rows, a count
+        // of 0 and NaN (NULL) for the rest
+        let mut df = DataFrame::new();
+        for (i, (_, a)) in aggs.iter().enumerate() {
+            let c = match a {
+                Agg::Count => Column::I(vec![0]),
+                _ => Column::D(vec![f64::NAN]),
+            };
+            df.addcol(&agg_name(i), c).map_err(|e| SqlError::Execution(e.to_string()))?;
+        }
+        df
+    } else {
+        g.finish()
+    };
+    let column = |o: Out| -> Result<Column> {
+        let source = match o {
+            Out::Key(i) => keys[i].clone(),
+            Out::Agg(i) => agg_name(i),
+        };
+        let Some(c) = grouped.get(&source) else {
+            return exec_err(format!("grouping did not produce column '{}'", source));
+        };
+        Ok(match o {
+            Out::Agg(i) => integral(c, &aggs[i], &w.df),
+            Out::Key(_) => c.clone(),
+        })
+    };
+
+    let mut out = DataFrame::new();
+    for (o, name) in outs {
+        add_output(&mut out, &name, column(o)?)?;
+    }
+    let mut sort_keys = Vec::new();
+    for (o, desc) in sorts {
+        let c = column(o)?;
+        sort_keys.push(((0..out.len()).map(|i| Scalar::at(&c, i)).collect(), desc));
+    }
+    let idx = window(argsort(&sort_keys, out.len()), q);
+    Ok(out.take(&idx))
+}
+
+/// an aggregate of an `I` column as `I` again for min and max, and for sums
+/// that fit. Missing results (NaN) keep the `D` column.
+fn integral(c: &Column, (col, agg): &(String, Agg), df: &DataFrame) -> Column {
+    match (c, df.get(col), agg) {
+        (Column::D(x), Some(Column::I(_)), Agg::Sum | Agg::Min | Agg::Max)
+            if x.iter().all(|v| v.is_finite() && *v >= i32::MIN as f64 && *v <= i32::MAX as f64) =>
+        {
+            Column::I(x.iter().map(|v| *v as i32).collect())
+        }
+        _ => c.clone(),
+    }
+}
+
+/// Named tables that queries can refer to
+#[derive(Default)]
+pub struct SqlContext {
+    tables: HashMap<String, DataFrame>,
+}
+
+impl SqlContext {
+    pub fn new() -> SqlContext {
+        SqlContext { tables: HashMap::new() }
+    }
+
+    /// register a frame as a table, replacing any table of the same name
+    pub fn register(&mut self, name: &str, df: DataFrame) {
+        self.tables.insert(name.to_string(), df);
+    }
+
+    pub fn deregister(&mut self, name: &str) -> Option<DataFrame> {
+        self.tables.remove(name)
+    }
+
+    pub fn table(&self, name: &str) -> Option<&DataFrame> {
+        self.tables.get(name)
+    }
+
+    /// names of the registered tables, sorted
+    pub fn tables(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.tables.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    fn working(&self, t: &TableRef) -> Result<Working> {
+        match self.tables.get(&t.name) {
+            Some(df) => Ok(Working::new(df, &t.alias)),
+            None => exec_err(format!("no table named '{}'", t.name)),
+        }
+    }
+
+    /// run a query, returning the result as a new frame
+    pub fn execute(&self, sql: &str) -> Result<DataFrame> {
+        let q = parse(sql)?;
+        let mut w = self.working(&q.from)?;
+        let mut aliases = vec![q.from.alias.as_str()];
+        for (t, on) in &q.joins {
+            if aliases.contains(&t.alias.as_str()) {
+                return exec_err(format!("table name '{}' used more than once, add an alias", t.alias));
+            }
+            aliases.push(&t.alias);
+            w = hash_join(w, self.working(t)?, on)?;
+        }
+
+        if let Some(f) = &q.filter {
+            if f.has_agg() {
+                return exec_err("aggregates are not allowed in WHERE");
+            }
+            let b = bind(f, &w)?;
+            let mask = (0..w.df.len()).map(|i| match eval(&b, i)? {
+                Scalar::Bool(x) => Ok(x),
+                v => exec_err(format!("WHERE needs a boolean, found a {}", v.type_name())),
+            }).collect::<Result<Vec<_>>>()?;
+            w = Working { df: w.df.filter(&mask), cols: w.cols };
+        }
+
+        let grouped = !q.group_by.is_empty()
+            || q.items.iter().any(|it| matches!(it, SelectItem::Expr(e, _) if e.has_agg()));
+        if grouped {
+            execute_grouped(w, &q)
+        } else {
+            execute_plain(w, &q)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::df;
+
+    fn ctx() -> SqlContext {
+        let mut ctx = SqlContext::new();
+        ctx.register("trades", df! {
+            "sym" => ["a", "b", "a", "c", "b"],
+            "qty" => [10, 20, 30, 40, 50],
+            "px" => [1.0, 2.0, 3.0, 4.0, 5.0],
+        });
+        ctx.register("names", df! {
+            "sym" => ["a", "b", "d"],
+            "name" => ["alpha", "beta", "delta"],
+        });
+        ctx
+    }
+
+    fn ints(df: &DataFrame, n: &str) -> Vec<i32> {
+        let Some(Column::I(x)) = df.get(n) else { panic!("expected I column '{}'", n) };
+        x.clone()
+    }
+
+    fn strs(df: &DataFrame, n: &str) -> Vec<String> {
+        let Some(Column::S(x)) = df.get(n) else { panic!("expected S column '{}'", n) };
+        x.clone()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let t = tokenize("SELECT a.b, 'it''s' FROM t WHERE x >= 1.5;").unwrap();
+        assert_eq!(t[1], Token::Ident("a".to_string()));
+        assert_eq!(t[2], Token::Sym("."));
+        assert_eq!(t[5], Token::Str("it's".to_string()));
+        assert!(t.contains(&Token::Sym(">=")));
+        assert!(t.contains(&Token::Number("1.5".to_string())));
+        assert!(tokenize("SELECT 'open").is_err());
+        assert!(tokenize("SELECT #").is_err());
+    }
+
+    #[test]
+    fn test_select_where() {
+        let out = ctx().execute("SELECT sym, qty * 2 AS dbl FROM trades WHERE px > 1.5 AND NOT sym = 'c'").unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["sym", "dbl"]);
+        assert_eq!(strs(&out, "sym"), vec!["b", "a", "b"]);
+        assert_eq!(ints(&out, "dbl"), vec![40, 60, 100]);
+
+        let out = ctx().execute("select * from trades where qty < 30").unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["sym", "qty", "px"]);
+        assert_eq!(out.len(), 2);
+    }
+
+    #[test]
+    fn test_order_limit() {
+        let out = ctx().execute("SELECT sym, qty FROM trades ORDER BY sym DESC, qty LIMIT 3 OFFSET 1").unwrap();
+        assert_eq!(strs(&out, "sym"), vec!["b", "b", "a"]);
+        assert_eq!(ints(&out, "qty"), vec![20, 50, 10]);
+
+        // order by an alias and by a column that is not selected
+        let out = ctx().execute("SELECT qty / 10 AS q FROM trades ORDER BY q DESC").unwrap();
+        let Some(Column::D(q)) = out.get("q") else { panic!("expected D column") };
+        assert_eq!(q, &vec![5.0, 4.0, 3.0, 2.0, 1.0]);
+        let out = ctx().execute("SELECT sym FROM trades ORDER BY px DESC LIMIT 1").unwrap();
+        assert_eq!(strs(&out, "sym"), vec!["b"]);
+    }
+
+    #[test]
+    fn test_group_by() {
+        let out = ctx().execute(
+            "SELECT sym, count(*) AS n, sum(qty), avg(px) FROM trades GROUP BY sym ORDER BY sum(qty) DESC"
+        ).unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["sym", "n", "sum(qty)", "avg(px)"]);
+        assert_eq!(strs(&out, "sym"), vec!["b", "a", "c"]);
+        assert_eq!(ints(&out, "n"), vec![2, 2, 1]);
+        assert_eq!(ints(&out, "sum(qty)"), vec![70, 40, 40]);
+
+        let out = ctx().execute("SELECT max(qty) AS m, MIN(qty) FROM trades WHERE sym <> 'b'").unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["m", "min(qty)"]);
+        assert_eq!(ints(&out, "m"), vec![40]);
+
+        // ORDER BY a key by its source name, an unselected aggregate, or an
+        // aggregate written another way
+        let out = ctx().execute("SELECT sym AS s, count(*) FROM trades GROUP BY sym ORDER BY sym DESC").unwrap();
+        assert_eq!(strs(&out, "s"), vec!["c", "b", "a"]);
+        let out = ctx().execute("SELECT sym FROM trades GROUP BY sym ORDER BY sum(qty), sym").unwrap();
+        assert_eq!(strs(&out, "sym"), vec!["a", "c", "b"]);
+        let out = ctx().execute("SELECT sym, avg(px) AS p FROM trades GROUP BY sym ORDER BY mean(px)").unwrap();
+        assert_eq!(out.len(), 3);
+
+        let out = ctx().execute("SELECT count(qty) AS n, sum(qty) AS s FROM trades WHERE qty > 100").unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(ints(&out, "n"), vec![0]);
+        let Some(Column::D(s)) = out.get("s") else { panic!("expected D column") };
+        assert!(s[0].is_nan());
+        let out = ctx().execute("SELECT sym, count(*) FROM trades WHERE qty > 100 GROUP BY sym").unwrap();
+        assert_eq!(out.len(), 0);
+
+        // repeated aggregates and keys are computed once
+        let out = ctx().execute("SELECT sym, sum(qty) AS a, sum(qty) AS b FROM trades GROUP BY sym ORDER BY sym").unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["sym", "a", "b"]);
+        assert_eq!(out.get("a"), out.get("b"));
+        let out = ctx().execute("SELECT sym, count(*) FROM trades GROUP BY sym, sym ORDER BY sym").unwrap();
+        assert_eq!(strs(&out, "sym"), vec!["a", "b", "c"]);
+        assert_eq!(ints(&out, "count(*)"), vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_join() {
+        let out = ctx().execute(
+            "SELECT t.sym, n.name, t.qty FROM trades t JOIN names AS n ON t.sym = n.sym ORDER BY qty"
+        ).unwrap();
+        assert_eq!(strs(&out, "name"), vec!["alpha", "beta", "alpha", "beta"]);
+        assert_eq!(ints(&out, "qty"), vec![10, 20, 30, 50]);
+
+        let out = ctx().execute("SELECT * FROM trades INNER JOIN names ON trades.sym = names.sym").unwrap();
+        assert_eq!(out.names().collect::<Vec<_>>(), vec!["trades.sym", "qty", "px", "names.sym", "name"]);
+        assert_eq!(out.len(), 4);
+
+        let out = ctx().execute(
+            "SELECT name, sum(qty) AS total FROM trades t JOIN names n ON t.sym = n.sym GROUP BY name ORDER BY name"
+        ).unwrap();
+        assert_eq!(strs(&out, "name"), vec!["alpha", "beta"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let c = ctx();
+        let err = |q: &str| c.execute(q).err().unwrap();
+        assert!(matches!(err("SELECT FROM trades"), SqlError::Parse(_)));
+        assert!(matches!(err("SELECT sym FROM trades extra junk"), SqlError::Parse(_)));
+        assert!(matches!(err("SELECT * FROM trades LEFT JOIN names ON trades.sym = names.sym"), SqlError::Parse(_)));
+        assert!(matches!(err("SELECT median(qty) FROM trades"), SqlError::Parse(_)));
+        assert_eq!(err("SELECT * FROM missing"), SqlError::Execution("no table named 'missing'".to_string()));
+        assert_eq!(err("SELECT nope FROM trades"), SqlError::Execution("no column named 'nope'".to_string()));
+        assert!(matches!(err("SELECT sym FROM trades t JOIN names n ON t.sym = n.sym"), SqlError::Execution(_)));
+        assert!(matches!(err("SELECT qty, sum(px) FROM trades GROUP BY sym"), SqlError::Execution(_)));
+        assert!(matches!(err("SELECT sym FROM trades WHERE qty"), SqlError::Execution(_)));
+        assert!(matches!(err("SELECT sym FROM trades WHERE sym > 1"), SqlError::Execution(_)));
+        assert_eq!(err("SELECT 1 +").to_string(), "parse error: expected identifier, found end of query");
+    }
+}
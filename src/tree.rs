@@ -1,9 +1,4 @@
-use std::{
-    sync::{atomic::AtomicI32, mpsc, Arc, Mutex},
-    thread, usize,
-};
-
-const NTHREADS: u32 = 4;
+use crate::pool::{self, Scope};
 
 /// given an array of index positions that represent a sort order, reorder given
 /// array by those indexes. Buffer is a slice of the same len as input that represents
@@ -20,13 +15,12 @@ fn sort_by_indexes(idxs: &[usize], a: &mut [i32], buffer: &mut [i32]) {
 }
 
 /// sort a dataframe by specific column index in place
-fn co_sort(df: &mut Vec<&mut [i32]>, by: usize) {
+fn co_sort(df: &mut [&mut [i32]], by: usize) {
     let mut arr_sort = (0..df[0].len()).collect::<Vec<_>>();
     arr_sort.sort_by_key(|k| df[by][*k]);
-    let mut sort_buffer = Vec::new();
-    sort_buffer.resize(df[0].len(), 0);
-    for i in 0..df.len() {
-        sort_by_indexes(&arr_sort, &mut df[i], &mut sort_buffer);
+    let mut sort_buffer = vec![0; df[0].len()];
+    for c in df.iter_mut() {
+        sort_by_indexes(&arr_sort, c, &mut sort_buffer);
     }
 }
 
@@ -46,8 +40,8 @@ fn multi_sort_and_split(mut df: Vec<&mut [i32]>, by: usize) -> (Vec<&mut [i32]>,
     (out_a, out_b)
 }
 
-/// Work management tracking structure. This is passed between threads to request
-/// a split be worked on and indicates the dimension on which the split was originally
+/// Work management tracking structure. Each split of the dataframe is a job
+/// on the pool, carrying the dimension on which the split was originally
 /// created (or usize MAX if this is the first)
 #[derive(Debug)]
 struct WorkUnit<'a> {
@@ -69,54 +63,33 @@ impl WorkUnit<'_> {
     }
 }
 
-/// work scheduling for iterative splits of input dataframe. Sends vectors of
-/// mutable slices (representing split nodes of dataframe) through channels to
-/// thread pool.
+/// split a node and queue both halves as new jobs
+fn split_node<'a>(scope: &Scope<'a>, r: WorkUnit<'a>) {
+    // stoping condition, here we just fake it with a condition
+    // of a min leaf size of 2.
+    if r.df[0].len() < 3 {
+        return;
+    }
+    let split_dim = r.new_split_dim();
+    let (a, b) = multi_sort_and_split(r.df, split_dim);
+    scope.spawn(move |s| split_node(s, WorkUnit { df: a, split_dim }));
+    scope.spawn(move |s| split_node(s, WorkUnit { df: b, split_dim }));
+}
+
+/// work scheduling for iterative splits of input dataframe. Each split node
+/// (a vector of mutable slices of the dataframe) is a job on the global
+/// `pool`, which returns once every node is split down to a leaf.
 ///
 /// TODO:
 ///
 /// * write tree structure after finding split
-pub fn par_mut_sorter_multi_arr(arr: &mut Vec<Vec<i32>>) {
-    let (send, recv) = mpsc::channel();
-    let recv = Arc::new(Mutex::new(recv));
-    let send = Arc::new(send);
-    let work_counter = Arc::new(AtomicI32::new(1));  // needs to be able to go negative
-    thread::scope(|scope| {
-        for _i in 0..NTHREADS {
-            let rc = Arc::clone(&recv);
-            let sc = Arc::clone(&send);
-            let wc = Arc::clone(&work_counter);
-            scope.spawn(move || {
-                loop {
-                    let r: WorkUnit = { rc.lock().unwrap().recv().unwrap() };
-                    // stoping condition, here we just fake it with a condition
-                    // of a min leaf size of 2.
-                    if r.df[0].len() < 3 {
-                        // only terminate if there is no pending work left, i.e.
-                        // the value before sub is 1 or less
-                        let pending = wc.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-                        if pending <= 1 {
-                            // signal is vector of single empty slice, passing above
-                            // condition - probably a better mechanism exists.
-                            let wu = WorkUnit { df: vec!(&mut [] as &mut[i32]), split_dim: 0 };
-                            sc.send(wu).unwrap();
-                            break;
-                        }
-                        continue;
-                    }
-                    wc.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                    let split_dim = r.new_split_dim();
-                    let (a, b) = multi_sort_and_split(r.df, split_dim);
-                    sc.send(WorkUnit { df: a, split_dim}).unwrap();
-                    sc.send(WorkUnit { df: b, split_dim}).unwrap();
-            }
-            });
-        }
+pub fn par_mut_sorter_multi_arr(arr: &mut [Vec<i32>]) {
+    pool::global().scope(|scope| {
         let init = WorkUnit {
             df: arr.iter_mut().map(|x| x.as_mut_slice()).collect::<Vec<_>>(),
             split_dim: usize::MAX,
         };
-        send.send(init).unwrap();
+        split_node(scope, init);
     });
 }
 
@@ -136,7 +109,7 @@ mod test {
 
     #[test]
     fn test_co_sort() {
-        let mut df = vec![
+        let mut df = [
             vec![1,2,3,4,4,3,2,1],
             vec![5,4,3,2,2,3,4,5],
             vec![9,3,2,8,5,7,1,0]